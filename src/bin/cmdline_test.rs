@@ -3,6 +3,7 @@ use bevy_egui::{EguiContexts, EguiPlugin, EguiPrimaryContextPass, egui};
 use n_body_platformer::commands::{
     DevCommandlinePlugin,
     command_parser::{DevCommand, DevCommandList},
+    console::ConsoleOutput,
 };
 
 fn main() {
@@ -13,18 +14,28 @@ fn main() {
         .add_systems(EguiPrimaryContextPass, ui_system);
 
     let commands = DevCommandList::new()
-        .add_command(DevCommand::<String>::new(
-            "info",
-            IntoSystem::into_system(info_cmd),
-            app.world_mut(),
-        ))
-        .add_command(DevCommand::new(
-            "infotwo",
-            IntoSystem::into_system(info_two),
-            app.world_mut(),
-        ));
-
-    app.add_plugins(DevCommandlinePlugin)
+        .add_command(
+            DevCommand::<String>::new("info", IntoSystem::into_system(info_cmd), app.world_mut())
+                .with_description("prints the text into the console"),
+        )
+        .add_command(
+            DevCommand::new(
+                "infotwo",
+                IntoSystem::into_system(info_two),
+                app.world_mut(),
+            )
+            .with_description("prints the text into the log"),
+        )
+        .add_command(
+            DevCommand::new(
+                "repeat",
+                IntoSystem::into_system(repeat_cmd),
+                app.world_mut(),
+            )
+            .with_description("prints the text into the console a number of times"),
+        );
+
+    app.add_plugins(DevCommandlinePlugin::from_args())
         .insert_resource(commands)
         .run();
 }
@@ -33,14 +44,21 @@ fn ui_system(mut context: EguiContexts) {
     egui::Window::new("Hello Gui").show(context.ctx_mut().unwrap(), |ui| ui.label("World"));
 }
 
-fn info_cmd(text: In<String>) {
-    info!("{}", text.0);
+fn info_cmd(text: In<String>, mut output: MessageWriter<ConsoleOutput>) {
+    output.write(ConsoleOutput::info(text.0));
 }
 
 fn info_two(text: In<String>) {
     info!("info_two: {}", text.0)
 }
 
+fn repeat_cmd(args: In<(u32, String)>, mut output: MessageWriter<ConsoleOutput>) {
+    let (count, text) = args.0;
+    for _ in 0..count {
+        output.write(ConsoleOutput::info(text.clone()));
+    }
+}
+
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2d);
 }
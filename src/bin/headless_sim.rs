@@ -0,0 +1,102 @@
+//! Runs the physics simulation without a window for a fixed number of steps
+//! and writes the state of every body as csv
+//!
+//! The output only depends on the arguments, so it can be used for golden file tests
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+};
+
+use n_body_platformer::{
+    headless::{HeadlessOptions, run_headless},
+    level_builder::LevelBuilder,
+};
+
+/// The level the sim_test binary opens with
+const SIM_TEST_LEVEL: &str = include_str!("../../assets/levels/sim_test.level.ron");
+
+const USAGE: &str = "Usage: headless_sim [--steps N] [--hz HZ] [--every N] \
+[--integrator euler|leapfrog|verlet|rk4] [--level FILE] [--output FILE]";
+
+#[derive(Default)]
+struct Options {
+    simulation: HeadlessOptions,
+    /// A `.level.ron` file, the built in level is used if not given
+    level: Option<String>,
+    output: Option<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", arg))
+        };
+
+        match arg.as_str() {
+            "--steps" => {
+                options.simulation.steps = value()?.parse().map_err(|e| format!("--steps: {e}"))?
+            }
+            "--hz" => options.simulation.hz = value()?.parse().map_err(|e| format!("--hz: {e}"))?,
+            "--every" => {
+                options.simulation.every =
+                    Some(value()?.parse().map_err(|e| format!("--every: {e}"))?)
+            }
+            "--integrator" => {
+                options.simulation.integrator =
+                    value()?.parse().map_err(|e| format!("--integrator: {e}"))?
+            }
+            "--level" => options.level = Some(value()?),
+            "--output" => options.output = Some(value()?),
+            "--help" | "-h" => return Err(String::new()),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+
+    if options.simulation.hz <= 0.0 {
+        return Err("--hz must be positive".to_string());
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let level = match &options.level {
+        Some(path) => match LevelBuilder::load(path) {
+            Ok(level) => level,
+            Err(e) => {
+                eprintln!("Could not load {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => LevelBuilder::from_ron(SIM_TEST_LEVEL).expect("The built in level should be valid"),
+    };
+
+    let mut output: Box<dyn Write> = match &options.output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(e) => {
+                eprintln!("Could not create {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    if let Err(e) = run_headless(&options.simulation, level, &mut output) {
+        eprintln!("Could not write output: {}", e);
+        std::process::exit(1);
+    }
+}
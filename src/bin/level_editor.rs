@@ -6,15 +6,9 @@ use n_body_platformer::mouse_camera_control::MainCameraMarker;
 use n_body_platformer::{
     level_builder::{LevelBuilder, LevelBuilderPlugin, PlanetBuilder},
     mouse_camera_control::MouseCameraControl,
-    physics::PhysicsPlugin,
+    physics::{PhysicsPlugin, PhysicsSettings, SimulationState},
 };
 
-#[derive(States, Debug, PartialEq, Eq, Clone, Hash)]
-enum SimulationState {
-    Running,
-    Paused,
-}
-
 #[derive(States, Debug, PartialEq, Eq, Clone, Hash, Default)]
 struct AlwaysOn;
 
@@ -44,11 +38,10 @@ fn main() {
             },
             PhysicsPlugin {
                 running_state: SimulationState::Running,
+                physics_settings: PhysicsSettings::default(),
             },
             LevelBuilderPlugin(lb),
-            EditingToolsPlugin::<MainCameraMarker> {
-                main_camera_type: std::marker::PhantomData,
-            },
+            EditingToolsPlugin::<MainCameraMarker>::default(),
             EguiPlugin::default(),
         ))
         .add_systems(Update, keyboard_state_changer)
@@ -63,11 +56,6 @@ fn keyboard_state_changer(
     mut next_sim_state: ResMut<NextState<SimulationState>>,
 ) {
     if keys.just_pressed(KeyCode::Space) {
-        use SimulationState::*;
-
-        next_sim_state.set(match sim_state.get() {
-            Paused => Running,
-            Running => Paused,
-        });
+        next_sim_state.set(sim_state.get().toggled());
     }
 }
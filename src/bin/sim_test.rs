@@ -1,7 +1,6 @@
 use std::time::Duration;
 
-use bevy::color::palettes::css::*;
-use bevy::{math::vec2, prelude::*};
+use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use n_body_platformer::commands::command_parser::DevCommandList;
 use n_body_platformer::commands::{CmdlineState, DevCommandlinePlugin};
@@ -10,50 +9,18 @@ use n_body_platformer::graphics::trails::{TrailUpdateConfig, draw_trail, update_
 use n_body_platformer::level_builder::LevelBuilderPlugin;
 use n_body_platformer::mouse_camera_control::{CameraSettings, MainCameraMarker};
 use n_body_platformer::{
-    level_builder::{LevelBuilder, PlanetBuilder},
+    level_builder::LevelBuilder,
     mouse_camera_control::MouseCameraControl,
-    physics::PhysicsPlugin,
+    physics::{PhysicsPlugin, PhysicsSettings, SimulationState, integrator::Integrator},
 };
 
-#[derive(States, Debug, PartialEq, Eq, Clone, Hash)]
-enum SimulationState {
-    Running,
-    Paused,
-}
-
 #[derive(States, Debug, PartialEq, Eq, Clone, Hash)]
 struct AlwaysOn;
 
 fn main() {
-    let level = LevelBuilder::default()
-        .add_planet(PlanetBuilder {
-            mass: 420000.0,
-            position: vec2(0.0, 0.0),
-            velocity: Some(vec2(0.0, 0.0)),
-            color: ORANGE.into(),
-            ..Default::default()
-        })
-        .add_planet(PlanetBuilder {
-            mass: 4200.0,
-            position: vec2(1600.0, 0.0),
-            velocity: Some(vec2(0.0, 150.0)),
-            color: GREEN.into(),
-            ..Default::default()
-        })
-        .add_planet(PlanetBuilder {
-            mass: 40.0,
-            position: vec2(1500.0, 0.0),
-            velocity: Some(vec2(0.0, 96.0)),
-            color: WHITE_SMOKE.into(),
-            ..Default::default()
-        })
-        .add_planet(PlanetBuilder {
-            mass: 3700.0,
-            position: vec2(600.0, 300.0),
-            velocity: Some(vec2(-180.0, 230.0)),
-            color: RED.into(),
-            ..Default::default()
-        });
+    //Embedded rather than loaded as an asset, the web demo is deployed without the assets folder
+    let level = LevelBuilder::from_ron(include_str!("../../assets/levels/sim_test.level.ron"))
+        .expect("The sim_test level should be valid");
 
     let mut app = App::new();
     app.add_plugins(DefaultPlugins)
@@ -68,6 +35,10 @@ fn main() {
             EguiPlugin::default(),
             PhysicsPlugin {
                 running_state: SimulationState::Running,
+                physics_settings: PhysicsSettings {
+                    integrator: Integrator::VelocityVerlet,
+                    ..Default::default()
+                },
             },
             LevelBuilderPlugin(level),
             EditingToolsPlugin::<MainCameraMarker>::default(),
@@ -83,7 +54,7 @@ fn main() {
     let dev_commands = DevCommandList::new().add_default_commands(app.world_mut());
 
     app.insert_resource(dev_commands)
-        .add_plugins(DevCommandlinePlugin);
+        .add_plugins(DevCommandlinePlugin::from_args());
 
     #[cfg(not(target_family = "wasm"))]
     app.insert_state(SimulationState::Paused);
@@ -105,11 +76,6 @@ fn keyboard_state_changer(
     }
 
     if keys.just_pressed(KeyCode::Space) {
-        use SimulationState::*;
-
-        next_sim_state.set(match sim_state.get() {
-            Paused => Running,
-            Running => Paused,
-        });
+        next_sim_state.set(sim_state.get().toggled());
     }
 }
@@ -0,0 +1,616 @@
+//! Typed arguments for dev commands
+//!
+//! The text after a command name is split on whitespace, double quotes keep a
+//! token with spaces together and `\"` puts a quote inside one. Each argument
+//! type takes its tokens off the front in order, so a command can take a tuple
+//! of arguments and an [`Option`] argument is allowed to run out of tokens.
+//!
+//! A [`String`] on its own is the rest of the line as it was typed, inside a
+//! tuple it is a single token.
+//!
+//! Types that only implement [`FromStr`] can be taken as a single token by
+//! wrapping them in [`FromStrArg`], or given a [`CommandArg`] impl with
+//! [`from_str_arg!`](crate::from_str_arg) in the crate they are defined in.
+
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use bevy::{
+    ecs::entity::{EntityGeneration, EntityRow},
+    prelude::*,
+};
+
+#[derive(Debug)]
+pub enum ArgumentError {
+    UnterminatedQuote,
+    Missing {
+        index: usize,
+        usage: String,
+    },
+    Invalid {
+        index: usize,
+        usage: String,
+        message: String,
+    },
+    TooMany {
+        extra: String,
+    },
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgumentError::UnterminatedQuote => write!(f, "unterminated quote"),
+            ArgumentError::Missing { index, usage } => {
+                write!(f, "argument {} {} is missing", index + 1, usage)
+            }
+            ArgumentError::Invalid {
+                index,
+                usage,
+                message,
+            } => write!(f, "argument {} {}: {}", index + 1, usage, message),
+            ArgumentError::TooMany { extra } => write!(f, "unexpected argument \"{}\"", extra),
+        }
+    }
+}
+
+impl Error for ArgumentError {}
+
+/// The tokens of a command line not yet taken by an argument
+pub struct ArgTokens {
+    tokens: Vec<String>,
+    next: usize,
+}
+
+impl ArgTokens {
+    pub fn split(line: &str) -> Result<Self, ArgumentError> {
+        let (tokens, open_quote) = tokenize(line);
+
+        if open_quote {
+            return Err(ArgumentError::UnterminatedQuote);
+        }
+
+        Ok(Self {
+            tokens: tokens.into_iter().map(|(_, token)| token).collect(),
+            next: 0,
+        })
+    }
+
+    /// Splits a line that is still being typed, where a quote can be left open
+    ///
+    /// Returns the finished tokens along with the last one and the byte index it
+    /// starts at, the last token is empty if the line ends in whitespace.
+    pub fn split_partial(line: &str) -> (Self, usize, String) {
+        let (mut tokens, open_quote) = tokenize(line);
+
+        let ends_in_token = open_quote || !(line.is_empty() || line.ends_with(char::is_whitespace));
+        let (start, last) = match tokens.pop() {
+            Some(last) if ends_in_token => last,
+            finished => {
+                tokens.extend(finished);
+                (line.len(), String::new())
+            }
+        };
+
+        let tokens = tokens.into_iter().map(|(_, token)| token).collect();
+        (Self { tokens, next: 0 }, start, last)
+    }
+
+    pub fn next_token(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.next)?;
+        self.next += 1;
+        Some(token)
+    }
+
+    pub fn peek_token(&self) -> Option<&str> {
+        self.tokens.get(self.next).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next >= self.tokens.len()
+    }
+
+    /// Number of tokens left
+    pub fn len(&self) -> usize {
+        self.tokens.len() - self.next
+    }
+}
+
+/// The tokens of a line with the byte index each starts at, and whether a quote was left open
+fn tokenize(line: &str) -> (Vec<(usize, String)>, bool) {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    //Quotes can make an empty token, so it isn't enough to check the text
+    let mut start = None;
+    let mut in_quotes = false;
+    let mut chars = line.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                start.get_or_insert(i);
+            }
+            '\\' if in_quotes => {
+                if let Some((_, escaped)) = chars.next() {
+                    token.push(escaped);
+                }
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if let Some(start) = start.take() {
+                    tokens.push((start, std::mem::take(&mut token)));
+                }
+            }
+            c => {
+                token.push(c);
+                start.get_or_insert(i);
+            }
+        }
+    }
+
+    if let Some(start) = start {
+        tokens.push((start, token));
+    }
+
+    (tokens, in_quotes)
+}
+
+/// A single argument of a dev command
+pub trait CommandArg: Sized {
+    /// What the argument is called in usage strings
+    fn name() -> String;
+
+    /// The argument in usage strings, `<name>` or `[name]` if it can be left out
+    fn usage() -> String {
+        format!("<{}>", Self::name())
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String>;
+
+    /// Values offered by tab completion
+    fn candidates() -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Takes the argument off the front of the tokens, none if they ran out
+    fn take(tokens: &mut ArgTokens) -> Result<Option<Self>, String> {
+        tokens.next_token().map(Self::parse_arg).transpose()
+    }
+
+    /// Parses a command line that only holds this argument
+    fn parse_line(line: &str) -> Result<Self, ArgumentError> {
+        let mut tokens = ArgTokens::split(line)?;
+        let arg = take_arg(&mut tokens, 0)?;
+        finish(&tokens)?;
+        Ok(arg)
+    }
+}
+
+/// Everything a dev command can take as its input
+pub trait CommandArgs: Sized + Send + Sync + 'static {
+    fn parse(line: &str) -> Result<Self, ArgumentError>;
+
+    /// The arguments as written after the command name, empty if there are none
+    fn usage() -> String;
+
+    /// Values offered by tab completion for the token at `index`
+    fn candidates(index: usize) -> Vec<String>;
+}
+
+impl<T> CommandArgs for T
+where
+    T: CommandArg + Send + Sync + 'static,
+{
+    fn parse(line: &str) -> Result<Self, ArgumentError> {
+        T::parse_line(line)
+    }
+
+    fn usage() -> String {
+        T::usage()
+    }
+
+    fn candidates(index: usize) -> Vec<String> {
+        match index {
+            0 => T::candidates(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl CommandArgs for () {
+    fn parse(line: &str) -> Result<Self, ArgumentError> {
+        finish(&ArgTokens::split(line)?)
+    }
+
+    fn usage() -> String {
+        String::new()
+    }
+
+    fn candidates(_index: usize) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+fn take_arg<T: CommandArg>(tokens: &mut ArgTokens, index: usize) -> Result<T, ArgumentError> {
+    match T::take(tokens) {
+        Ok(Some(arg)) => Ok(arg),
+        Ok(None) => Err(ArgumentError::Missing {
+            index,
+            usage: T::usage(),
+        }),
+        Err(message) => Err(ArgumentError::Invalid {
+            index,
+            usage: T::usage(),
+            message,
+        }),
+    }
+}
+
+fn finish(tokens: &ArgTokens) -> Result<(), ArgumentError> {
+    match tokens.peek_token() {
+        Some(extra) => Err(ArgumentError::TooMany {
+            extra: extra.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+macro_rules! tuple_args {
+    ($($arg:ident $index:tt),+) => {
+        impl<$($arg),+> CommandArgs for ($($arg,)+)
+        where
+            $($arg: CommandArg + Send + Sync + 'static),+
+        {
+            fn parse(line: &str) -> Result<Self, ArgumentError> {
+                let mut tokens = ArgTokens::split(line)?;
+                let args = ($(take_arg::<$arg>(&mut tokens, $index)?,)+);
+                finish(&tokens)?;
+                Ok(args)
+            }
+
+            fn usage() -> String {
+                [$($arg::usage()),+].join(" ")
+            }
+
+            fn candidates(index: usize) -> Vec<String> {
+                match index {
+                    $($index => $arg::candidates(),)+
+                    _ => Vec::new(),
+                }
+            }
+        }
+    };
+}
+
+tuple_args!(A 0, B 1);
+tuple_args!(A 0, B 1, C 2);
+tuple_args!(A 0, B 1, C 2, D 3);
+tuple_args!(A 0, B 1, C 2, D 3, E 4);
+tuple_args!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+macro_rules! number_arg {
+    ($($number:ty),+) => {
+        $(
+            impl CommandArg for $number {
+                fn name() -> String {
+                    stringify!($number).to_string()
+                }
+
+                fn parse_arg(token: &str) -> Result<Self, String> {
+                    token.parse().map_err(|e| format!("{} \"{}\"", e, token))
+                }
+            }
+        )+
+    };
+}
+
+number_arg!(f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl CommandArg for bool {
+    fn name() -> String {
+        "bool".to_string()
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String> {
+        match token.to_lowercase().as_str() {
+            "true" | "on" | "yes" | "1" => Ok(true),
+            "false" | "off" | "no" | "0" => Ok(false),
+            _ => Err(format!("\"{}\" is not true or false", token)),
+        }
+    }
+
+    fn candidates() -> Vec<String> {
+        vec!["true".to_string(), "false".to_string()]
+    }
+}
+
+impl CommandArg for String {
+    fn name() -> String {
+        "text".to_string()
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String> {
+        Ok(token.to_string())
+    }
+
+    fn parse_line(line: &str) -> Result<Self, ArgumentError> {
+        Ok(line.trim().to_string())
+    }
+}
+
+impl<T: CommandArg> CommandArg for Option<T> {
+    fn name() -> String {
+        T::name()
+    }
+
+    fn usage() -> String {
+        format!("[{}]", T::name())
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String> {
+        T::parse_arg(token).map(Some)
+    }
+
+    fn candidates() -> Vec<String> {
+        T::candidates()
+    }
+
+    fn take(tokens: &mut ArgTokens) -> Result<Option<Self>, String> {
+        if tokens.is_empty() {
+            return Ok(Some(None));
+        }
+
+        T::take(tokens).map(|arg| arg.map(Some))
+    }
+}
+
+/// Written as `x y` or `x,y`
+impl CommandArg for Vec2 {
+    fn name() -> String {
+        "x y".to_string()
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String> {
+        let token = token.trim_start_matches('(').trim_end_matches(')');
+        let Some((x, y)) = token.split_once(',') else {
+            return Err(format!("\"{}\" is not a vector", token));
+        };
+
+        Ok(Vec2::new(
+            f32::parse_arg(x.trim())?,
+            f32::parse_arg(y.trim())?,
+        ))
+    }
+
+    fn take(tokens: &mut ArgTokens) -> Result<Option<Self>, String> {
+        let Some(x) = tokens.next_token().map(str::to_string) else {
+            return Ok(None);
+        };
+
+        if x.contains(',') {
+            return Vec2::parse_arg(&x).map(Some);
+        }
+
+        let x = f32::parse_arg(&x)?;
+        let y = tokens
+            .next_token()
+            .ok_or_else(|| "missing the y coordinate".to_string())
+            .and_then(f32::parse_arg)?;
+
+        Ok(Some(Vec2::new(x, y)))
+    }
+}
+
+/// Written as the index, optionally followed by the generation as entities are displayed, `12v1`
+impl CommandArg for Entity {
+    fn name() -> String {
+        "entity".to_string()
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String> {
+        let (index, generation) = match token.split_once('v') {
+            Some((index, generation)) => (index, Some(generation)),
+            None => (token, None),
+        };
+
+        let row = index
+            .parse()
+            .ok()
+            .and_then(EntityRow::from_raw_u32)
+            .ok_or_else(|| format!("\"{}\" is not an entity", token))?;
+        let generation = match generation {
+            Some(generation) => generation
+                .parse()
+                .map(EntityGeneration::from_bits)
+                .map_err(|_| format!("\"{}\" is not an entity", token))?,
+            None => EntityGeneration::FIRST,
+        };
+
+        Ok(Entity::from_row_and_generation(row, generation))
+    }
+}
+
+/// Takes any [`FromStr`] type as a single token
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FromStrArg<T>(pub T);
+
+impl<T> CommandArg for FromStrArg<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn name() -> String {
+        ShortName::of::<T>().to_string()
+    }
+
+    fn parse_arg(token: &str) -> Result<Self, String> {
+        token
+            .parse()
+            .map(FromStrArg)
+            .map_err(|e: T::Err| e.to_string())
+    }
+}
+
+/// Implements [`CommandArg`] through [`FromStr`] taking a single token, e.g.
+/// `from_str_arg!(Integrator, "integrator");`
+#[macro_export]
+macro_rules! from_str_arg {
+    ($type:ty, $name:expr) => {
+        impl $crate::commands::arguments::CommandArg for $type {
+            fn name() -> String {
+                $name.to_string()
+            }
+
+            fn parse_arg(token: &str) -> Result<Self, String> {
+                token.parse().map_err(|e| format!("{}", e))
+            }
+        }
+    };
+}
+
+from_str_arg!(crate::physics::integrator::Integrator, "integrator");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(line: &str) -> Vec<String> {
+        let mut tokens = ArgTokens::split(line).unwrap();
+        let mut split = Vec::new();
+
+        while let Some(token) = tokens.next_token() {
+            split.push(token.to_string());
+        }
+
+        split
+    }
+
+    #[test]
+    fn splits_on_whitespace_and_quotes() {
+        assert_eq!(tokens("  a  bc\td "), ["a", "bc", "d"]);
+        assert_eq!(tokens(r#"a "b c" d"#), ["a", "b c", "d"]);
+        assert_eq!(tokens(r#"x"y z"w"#), ["xy zw"]);
+        assert_eq!(
+            tokens(r#""say \"hi\"" "back\\slash""#),
+            [r#"say "hi""#, r"back\slash"]
+        );
+        assert_eq!(tokens(r#""" a """#), ["", "a", ""]);
+        assert!(tokens("").is_empty());
+    }
+
+    #[test]
+    fn unterminated_quotes_are_errors() {
+        assert!(matches!(
+            ArgTokens::split(r#"a "b c"#),
+            Err(ArgumentError::UnterminatedQuote)
+        ));
+        assert!(matches!(
+            ArgTokens::split(r#""ends in escape\"#),
+            Err(ArgumentError::UnterminatedQuote)
+        ));
+    }
+
+    #[test]
+    fn partial_lines_end_in_the_token_being_typed() {
+        let (tokens, start, last) = ArgTokens::split_partial(r#"a "b c"#);
+        assert_eq!((tokens.len(), start, last.as_str()), (1, 2, "b c"));
+
+        let (tokens, start, last) = ArgTokens::split_partial("a b ");
+        assert_eq!((tokens.len(), start, last.as_str()), (2, 4, ""));
+
+        let (tokens, start, last) = ArgTokens::split_partial("");
+        assert_eq!((tokens.len(), start, last.as_str()), (0, 0, ""));
+    }
+
+    #[test]
+    fn parses_tuples() {
+        let (speed, name, on) = <(f32, String, bool)>::parse(r#"1.5 "two words" yes"#).unwrap();
+
+        assert_eq!(speed, 1.5);
+        assert_eq!(name, "two words");
+        assert!(on);
+        assert_eq!(<(f32, String, bool)>::usage(), "<f32> <text> <bool>");
+    }
+
+    #[test]
+    fn string_alone_takes_the_whole_line() {
+        assert_eq!(String::parse("  a b  c ").unwrap(), "a b  c");
+    }
+
+    #[test]
+    fn options_can_run_out() {
+        assert_eq!(<Option<u32>>::parse("").unwrap(), None);
+        assert_eq!(<Option<u32>>::parse("7").unwrap(), Some(7));
+        assert_eq!(<(u32, Option<u32>)>::parse("3").unwrap(), (3, None));
+        assert_eq!(<(u32, Option<u32>)>::usage(), "<u32> [u32]");
+    }
+
+    #[test]
+    fn parses_vectors() {
+        assert_eq!(Vec2::parse("1 -2").unwrap(), vec2(1.0, -2.0));
+        assert_eq!(Vec2::parse("1,-2").unwrap(), vec2(1.0, -2.0));
+        assert_eq!(Vec2::parse("(1,-2)").unwrap(), vec2(1.0, -2.0));
+        assert_eq!(
+            <(Vec2, f32)>::parse("1 2 3").unwrap(),
+            (vec2(1.0, 2.0), 3.0)
+        );
+        assert!(Vec2::parse("1").is_err());
+    }
+
+    #[test]
+    fn parses_entities() {
+        let entity = Entity::from_row_and_generation(
+            EntityRow::from_raw_u32(12).unwrap(),
+            EntityGeneration::FIRST,
+        );
+
+        assert_eq!(Entity::parse("12").unwrap(), entity);
+        assert_eq!(Entity::parse(&entity.to_string()).unwrap(), entity);
+        assert!(Entity::parse("twelve").is_err());
+        assert!(Entity::parse("12vx").is_err());
+    }
+
+    #[test]
+    fn parses_from_str_types() {
+        use std::net::Ipv4Addr;
+
+        assert_eq!(
+            <FromStrArg<Ipv4Addr>>::parse("127.0.0.1").unwrap(),
+            FromStrArg(Ipv4Addr::LOCALHOST)
+        );
+        assert_eq!(<FromStrArg<Ipv4Addr> as CommandArgs>::usage(), "<Ipv4Addr>");
+        assert!(matches!(
+            <FromStrArg<Ipv4Addr>>::parse("local"),
+            Err(ArgumentError::Invalid { index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn reports_missing_arguments() {
+        let error = <(u32, u32)>::parse("1").unwrap_err();
+
+        assert!(matches!(error, ArgumentError::Missing { index: 1, .. }));
+        assert_eq!(error.to_string(), "argument 2 <u32> is missing");
+    }
+
+    #[test]
+    fn reports_invalid_arguments() {
+        let error = <(u32, bool)>::parse("1 maybe").unwrap_err();
+
+        assert!(matches!(error, ArgumentError::Invalid { index: 1, .. }));
+        assert_eq!(
+            error.to_string(),
+            r#"argument 2 <bool>: "maybe" is not true or false"#
+        );
+    }
+
+    #[test]
+    fn reports_extra_arguments() {
+        let error = <()>::parse("extra").unwrap_err();
+        assert_eq!(error.to_string(), r#"unexpected argument "extra""#);
+
+        assert!(matches!(
+            u32::parse("1 2"),
+            Err(ArgumentError::TooMany { extra }) if extra == "2"
+        ));
+    }
+}
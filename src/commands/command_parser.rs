@@ -1,6 +1,20 @@
 use bevy::{ecs::system::SystemId, prelude::*};
-use radix_trie::Trie;
-use std::{error::Error, str::FromStr};
+use radix_trie::{Trie, TrieCommon};
+
+use super::{
+    arguments::CommandArgs,
+    completion::{ArgCompletion, complete_file_paths, complete_planets},
+    console::ConsoleOutput,
+    script::exec_command,
+    simulation::{gravity, pause, planet, reset, resume, step, timestep, unfreeze},
+};
+use crate::physics::diagnostics::PhysicsDiagnostics;
+
+const CONSOLE_HELP: &[&str] = &[
+    "Type a command and press Enter to run it, <arguments> are required and [arguments] optional",
+    "Tab completes, Up and Down recall earlier commands, Page Up and Page Down scroll",
+    "'commands' lists every command, 'help <command>' describes one",
+];
 
 #[derive(Resource)]
 pub struct DevCommandList(pub(super) Trie<String, Box<dyn Runnable + Sync + Send>>);
@@ -10,49 +24,273 @@ impl DevCommandList {
         Self(Trie::new())
     }
 
+    /// # Panics
+    /// If the name has whitespace in it, or a command with the same name was already added
     pub fn add_command<I>(mut self, dev_command: DevCommand<I>) -> Self
     where
-        I: FromStr + Send + Sync,
-        I::Err: Error + 'static,
+        I: CommandArgs,
     {
-        self.0
-            .insert(dev_command.name.to_string(), Box::new(dev_command));
+        self.insert(dev_command);
         self
     }
 
-    /// Default commands include the following commands:
-    /// - ```setclockspeed [f32]``` sets a multiplier on speed that the bevy clock advances by
+    fn insert<I>(&mut self, dev_command: DevCommand<I>)
+    where
+        I: CommandArgs,
+    {
+        let name = dev_command.name;
+
+        assert!(
+            !name.is_empty() && !name.contains(char::is_whitespace),
+            "Dev command name \"{}\" must be a single word",
+            name
+        );
+        assert!(
+            self.0.get(name).is_none(),
+            "Dev command \"{}\" was added twice",
+            name
+        );
+
+        self.0.insert(name.to_string(), Box::new(dev_command));
+    }
+
+    /// Runs the command the line starts with, the error says why it couldn't be run
+    pub(super) fn run_line(&self, commands: &mut Commands, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let dev_command = self
+            .0
+            .get(name)
+            .ok_or_else(|| format!("Unknown command: {}", name))?;
+
+        match dev_command.run(commands, args.trim()) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Names of every command in alphabetical order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.0.keys().map(String::as_str).collect();
+        names.sort();
+        names
+    }
+
+    /// Adds ```help```, ```commands``` and ```exec``` if they are missing
+    pub(super) fn add_builtin_commands(&mut self, world: &mut World) {
+        if self.0.get("help").is_none() {
+            self.insert(
+                DevCommand::new("help", IntoSystem::into_system(help), world)
+                    .with_description("describes a command, or how to use the console")
+                    .with_completer(IntoSystem::into_system(complete_command_names), world),
+            );
+        }
+        if self.0.get("commands").is_none() {
+            self.insert(
+                DevCommand::new("commands", IntoSystem::into_system(list_commands), world)
+                    .with_description("lists every command"),
+            );
+        }
+        if self.0.get("exec").is_none() {
+            self.insert(
+                DevCommand::new("exec", IntoSystem::into_system(exec_command), world)
+                    .with_description("runs every line of a file as a command")
+                    .with_completer(IntoSystem::into_system(complete_file_paths), world),
+            );
+        }
+    }
+
+    /// ```help```, ```commands``` and ```exec``` are always added by the [`DevCommandlinePlugin`](super::DevCommandlinePlugin),
+    /// default commands include the following commands:
+    /// - ```setclockspeed <f32>``` sets a multiplier on speed that the bevy clock advances by
+    /// - ```pause``` and ```resume``` switch the [`SimulationState`](crate::physics::SimulationState)
+    /// - ```step [n]``` pauses and advances the simulation by n fixed updates, 1 if not given
+    /// - ```reset``` rebuilds the level the app started with, or the last one opened
+    /// - ```timestep <hz>``` sets the rate of the fixed updates
+    /// - ```gravity [G]``` sets the gravitational constant, 0 or above, or prints it if not given
+    /// - ```diagnostics [text]``` logs the energy and momentum of the simulation, ```diagnostics reset```
+    ///   measures drift from the current state
     pub fn add_default_commands(self, world: &mut World) -> Self {
-        self.add_command(DevCommand::new(
-            "setclockspeed",
-            IntoSystem::into_system(set_speed_multiplier),
-            world,
-        ))
+        self.add_command(
+            DevCommand::new(
+                "setclockspeed",
+                IntoSystem::into_system(set_speed_multiplier),
+                world,
+            )
+            .with_description("sets a multiplier on the speed of the clock"),
+        )
+        .add_command(
+            DevCommand::new("pause", IntoSystem::into_system(pause), world)
+                .with_description("pauses the simulation"),
+        )
+        .add_command(
+            DevCommand::new("resume", IntoSystem::into_system(resume), world)
+                .with_description("resumes the simulation"),
+        )
+        .add_command(
+            DevCommand::new("step", IntoSystem::into_system(step), world)
+                .with_description("advances the paused simulation by a number of fixed updates"),
+        )
+        .add_command(
+            DevCommand::new("reset", IntoSystem::into_system(reset), world)
+                .with_description("rebuilds the level it started with"),
+        )
+        .add_command(
+            DevCommand::new("timestep", IntoSystem::into_system(timestep), world)
+                .with_description("sets the rate of the fixed updates in hz"),
+        )
+        .add_command(
+            DevCommand::new("gravity", IntoSystem::into_system(gravity), world)
+                .with_description("sets or prints the gravitational constant"),
+        )
+        .add_command(
+            DevCommand::new("setgravity", IntoSystem::into_system(gravity), world)
+                .with_description("same as gravity"),
+        )
+        .add_command(
+            DevCommand::new("planet", IntoSystem::into_system(planet), world)
+                .with_description("prints the mass, position and velocity of a planet")
+                .with_completer(complete_planets, world),
+        )
+        .add_command(
+            DevCommand::new("unfreeze", IntoSystem::into_system(unfreeze), world)
+                .with_description(
+                    "puts planets frozen for a non-finite state back into the simulation",
+                )
+                .with_completer(complete_planets, world),
+        )
+        .add_command(
+            DevCommand::new(
+                "diagnostics",
+                IntoSystem::into_system(physics_diagnostics),
+                world,
+            )
+            .with_description(
+                "prints energy and momentum, 'diagnostics reset' measures drift from now",
+            ),
+        )
     }
 }
 
+fn help(
+    command: In<Option<String>>,
+    dev_commands: Res<DevCommandList>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let Some(name) = command.0 else {
+        for line in CONSOLE_HELP {
+            output.write(ConsoleOutput::info(*line));
+        }
+        return;
+    };
+
+    match dev_commands.0.get(name.as_str()) {
+        Some(dev_command) => {
+            output.write(ConsoleOutput::info(dev_command.usage()));
+
+            if !dev_command.description().is_empty() {
+                output.write(ConsoleOutput::info(format!(
+                    "    {}",
+                    dev_command.description()
+                )));
+            }
+        }
+        None => {
+            output.write(ConsoleOutput::error(format!("Unknown command: {}", name)));
+        }
+    }
+}
+
+fn list_commands(
+    _: In<()>,
+    dev_commands: Res<DevCommandList>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    for name in dev_commands.names() {
+        let Some(dev_command) = dev_commands.0.get(name) else {
+            continue;
+        };
+
+        output.write(ConsoleOutput::info(match dev_command.description() {
+            "" => name.to_string(),
+            description => format!("{:<16}{}", name, description),
+        }));
+    }
+}
+
+fn complete_command_names(
+    _request: In<ArgCompletion>,
+    dev_commands: Res<DevCommandList>,
+) -> Vec<String> {
+    dev_commands
+        .names()
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
 impl Default for DevCommandList {
     fn default() -> Self {
         Self::new()
     }
 }
 
-fn set_speed_multiplier(speed: In<f32>, mut time: ResMut<Time<Virtual>>) {
-    info!("Sim speed multiplier set to: {}", speed.0);
+fn set_speed_multiplier(
+    speed: In<f32>,
+    mut time: ResMut<Time<Virtual>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    output.write(ConsoleOutput::info(format!(
+        "Sim speed multiplier set to: {}",
+        speed.0
+    )));
     time.set_relative_speed(speed.0);
 }
 
+fn physics_diagnostics(
+    args: In<Option<String>>,
+    diagnostics: Option<ResMut<PhysicsDiagnostics>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let Some(mut diagnostics) = diagnostics else {
+        output.write(ConsoleOutput::error(
+            "Physics are not running, no diagnostics available",
+        ));
+        return;
+    };
+
+    match args.0.as_deref() {
+        None => {
+            for line in diagnostics.summary() {
+                output.write(ConsoleOutput::info(line));
+            }
+        }
+        Some("reset") => {
+            diagnostics.reset_baseline();
+            output.write(ConsoleOutput::info("Diagnostics baseline reset"));
+        }
+        Some(other) => {
+            output.write(ConsoleOutput::error(format!(
+                "Unknown diagnostics argument: {}",
+                other
+            )));
+        }
+    }
+}
+
 pub struct DevCommand<I>
 where
     I: Send + Sync + 'static,
 {
     name: &'static str,
+    description: &'static str,
     pub(super) system_id: SystemId<In<I>, ()>,
+    completer: Option<SystemId<In<ArgCompletion>, Vec<String>>>,
 }
 
 impl<I> DevCommand<I>
 where
-    I: FromStr + Send + Sync + 'static,
+    I: CommandArgs,
 {
     pub fn new(
         name: &'static str,
@@ -61,35 +299,143 @@ where
     ) -> Self {
         Self {
             name,
+            description: "",
             system_id: world.register_system(system),
+            completer: None,
+        }
+    }
+
+    /// What the command does, shown by ```help``` and ```commands```
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Completes the arguments with a system instead of the values their types list
+    pub fn with_completer<M>(
+        mut self,
+        completer: impl IntoSystem<In<ArgCompletion>, Vec<String>, M> + 'static,
+        world: &mut World,
+    ) -> Self {
+        self.completer = Some(world.register_system(completer));
+        self
+    }
+
+    /// The command name followed by its arguments, `<required>` or `[optional]`
+    pub fn usage(&self) -> String {
+        match I::usage() {
+            args if args.is_empty() => self.name.to_string(),
+            args => format!("{} {}", self.name, args),
         }
     }
 }
 
 pub(super) trait Runnable {
     fn run(&self, commands: &mut Commands, args: &str) -> Option<String>;
-    fn prefix(&self) -> &'static str;
+    fn usage(&self) -> String;
+    fn description(&self) -> &'static str;
+    fn completer(&self) -> Option<SystemId<In<ArgCompletion>, Vec<String>>>;
+    /// Values the argument types list for the token at `index`
+    fn candidates(&self, index: usize) -> Vec<String>;
 }
 
 impl<I> Runnable for DevCommand<I>
 where
-    I: FromStr + Send + Sync + 'static,
-    I::Err: Error + 'static,
+    I: CommandArgs,
 {
     fn run(&self, commands: &mut Commands, args: &str) -> Option<String> {
-        match args.parse() {
+        match I::parse(args) {
             Ok(args) => {
                 commands.run_system_with(self.system_id, args);
                 None
             }
             Err(e) => {
-                warn!("Error running command {}: {}", self.name, e);
-                Some(e.to_string())
+                warn!(
+                    "Error running command {}: {}, usage: {}",
+                    self.name,
+                    e,
+                    self.usage()
+                );
+                Some(format!("{}, usage: {}", e, self.usage()))
             }
         }
     }
 
-    fn prefix(&self) -> &'static str {
-        self.name
+    fn usage(&self) -> String {
+        DevCommand::usage(self)
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn completer(&self) -> Option<SystemId<In<ArgCompletion>, Vec<String>>> {
+        self.completer
+    }
+
+    fn candidates(&self, index: usize) -> Vec<String> {
+        I::candidates(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::world::CommandQueue;
+
+    use super::*;
+    use crate::{commands::arguments::FromStrArg, physics::integrator::Integrator};
+
+    #[derive(Resource, Default)]
+    struct Ran(Vec<String>);
+
+    fn run(world: &mut World, list: &DevCommandList, line: &str) -> Result<(), String> {
+        let mut queue = CommandQueue::default();
+        let result = list.run_line(&mut Commands::new(&mut queue, world), line);
+        queue.apply(world);
+        result
+    }
+
+    #[test]
+    fn runs_commands_taking_from_str_types() {
+        let mut world = World::new();
+        world.init_resource::<Ran>();
+
+        let list = DevCommandList::new()
+            .add_command(DevCommand::new(
+                "integrator",
+                IntoSystem::into_system(|In(integrator): In<Integrator>, mut ran: ResMut<Ran>| {
+                    ran.0.push(format!("{:?}", integrator));
+                }),
+                &mut world,
+            ))
+            .add_command(DevCommand::new(
+                "float",
+                IntoSystem::into_system(
+                    |In(FromStrArg(value)): In<FromStrArg<f64>>, mut ran: ResMut<Ran>| {
+                        ran.0.push(value.to_string());
+                    },
+                ),
+                &mut world,
+            ));
+
+        run(&mut world, &list, "integrator rk4").unwrap();
+        run(&mut world, &list, "float 2.5").unwrap();
+        assert!(run(&mut world, &list, "integrator sideways").is_err());
+
+        assert_eq!(world.resource::<Ran>().0, ["Rk4", "2.5"]);
+    }
+
+    #[test]
+    fn builtins_sit_next_to_commands_they_prefix() {
+        let mut world = World::new();
+
+        let mut list = DevCommandList::new().add_command(DevCommand::new(
+            "helpful",
+            IntoSystem::into_system(|_: In<()>| {}),
+            &mut world,
+        ));
+        list.add_builtin_commands(&mut world);
+
+        assert_eq!(list.names(), ["commands", "exec", "help", "helpful"]);
     }
 }
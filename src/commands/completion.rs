@@ -0,0 +1,158 @@
+//! Tab completion for the dev console
+//!
+//! The word under the cursor is completed to the longest prefix shared by all
+//! candidates, when that is ambiguous the candidates are printed and pressing
+//! Tab again cycles through them. Command names complete from the
+//! [`DevCommandList`], arguments from the completer system of the command or,
+//! without one, the values their [`CommandArg`](super::arguments::CommandArg)
+//! type lists.
+
+use std::fs;
+
+use bevy::prelude::*;
+
+use super::{arguments::ArgTokens, command_parser::DevCommandList, console::DevConsole};
+use crate::physics::PhysicsMaterial;
+
+/// The argument a completer system is asked to complete
+#[derive(Clone, Debug)]
+pub struct ArgCompletion {
+    /// Index of the token being typed after the command name
+    pub index: usize,
+    /// What has been typed of it so far
+    pub partial: String,
+}
+
+/// Completes the input of the console, queued when Tab is pressed since
+/// completer systems need the world
+pub(super) fn complete_cmdline(world: &mut World) {
+    world.resource_scope(|world, mut console: Mut<DevConsole>| {
+        if console.cycle_completion() {
+            return;
+        }
+
+        let before_cursor = console.split_input().0.to_string();
+
+        if let Some((word_start, candidates)) = completions(world, &before_cursor) {
+            console.complete(word_start, candidates);
+        }
+    });
+}
+
+/// Candidates for the word at the end of `line` and the byte index it starts at
+fn completions(world: &mut World, line: &str) -> Option<(usize, Vec<String>)> {
+    let dev_commands = world.get_resource::<DevCommandList>()?;
+    let command_line = line.trim_start();
+
+    let Some((name, args)) = command_line.split_once(char::is_whitespace) else {
+        let names = dev_commands
+            .names()
+            .into_iter()
+            .filter(|name| name.starts_with(command_line))
+            .map(str::to_string)
+            .collect();
+
+        return Some((line.len() - command_line.len(), names));
+    };
+
+    let (tokens, partial_start, partial) = ArgTokens::split_partial(args);
+    let request = ArgCompletion {
+        index: tokens.len(),
+        partial,
+    };
+
+    let command = dev_commands.0.get(name)?;
+    let completer = command.completer();
+    let candidates = command.candidates(request.index);
+
+    let candidates = match completer {
+        Some(completer) => world
+            .run_system_with(completer, request.clone())
+            .unwrap_or_else(|e| {
+                warn!("Could not complete arguments of {}: {}", name, e);
+                Vec::new()
+            }),
+        None => candidates,
+    };
+
+    let mut candidates: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(&request.partial))
+        .map(quote)
+        .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    Some((line.len() - args.len() + partial_start, candidates))
+}
+
+/// Quotes a candidate if it wouldn't be read back as a single token
+fn quote(candidate: String) -> String {
+    if candidate.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!(
+            "\"{}\"",
+            candidate.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    } else {
+        candidate
+    }
+}
+
+pub(super) fn longest_common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+
+    let end = candidates[1..].iter().fold(first.len(), |end, candidate| {
+        first[..end]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(candidate.len()), |((i, _), _)| i)
+    });
+
+    &first[..end]
+}
+
+/// Completes paths relative to the working directory, directories end with a `/`
+pub fn complete_file_paths(request: In<ArgCompletion>) -> Vec<String> {
+    let (directory, file) = match request.partial.rsplit_once('/') {
+        Some((directory, file)) => (format!("{}/", directory), file),
+        None => (String::new(), request.partial.as_str()),
+    };
+
+    let search = if directory.is_empty() {
+        "."
+    } else {
+        &directory
+    };
+    let Ok(entries) = fs::read_dir(search) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let suffix = if entry.file_type().ok()?.is_dir() {
+                "/"
+            } else {
+                ""
+            };
+
+            name.starts_with(file)
+                .then(|| format!("{}{}{}", directory, name, suffix))
+        })
+        .collect()
+}
+
+/// Completes the planets in the world, as entities are written for an argument
+pub fn complete_planets(
+    _request: In<ArgCompletion>,
+    planet_query: Query<Entity, With<PhysicsMaterial>>,
+) -> Vec<String> {
+    planet_query
+        .iter()
+        .map(|entity| entity.to_string())
+        .collect()
+}
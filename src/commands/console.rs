@@ -0,0 +1,317 @@
+//! State of the dev console: the line being typed, the scrollback log and the
+//! history of entered commands
+//!
+//! Command systems write to the console by sending a [`ConsoleOutput`]. The
+//! history is saved to [`HISTORY_FILE`] after every command and loaded again
+//! when the console plugin starts.
+
+use std::{fs, io, path::Path};
+
+use bevy::prelude::*;
+
+use super::completion::longest_common_prefix;
+
+pub const HISTORY_FILE: &str = "console_history.txt";
+const MAX_HISTORY: usize = 200;
+const MAX_SCROLLBACK: usize = 500;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConsoleLineKind {
+    /// A command that was entered
+    Command,
+    Info,
+    Error,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConsoleLine {
+    pub text: String,
+    pub kind: ConsoleLineKind,
+}
+
+/// A line of output for the dev console, also written to the log
+#[derive(Message, Clone, Debug)]
+pub struct ConsoleOutput(pub ConsoleLine);
+
+impl ConsoleOutput {
+    pub fn info(text: impl Into<String>) -> Self {
+        Self(ConsoleLine {
+            text: text.into(),
+            kind: ConsoleLineKind::Info,
+        })
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self(ConsoleLine {
+            text: text.into(),
+            kind: ConsoleLineKind::Error,
+        })
+    }
+}
+
+/// Candidates of an ambiguous completion, cycled through by pressing Tab again
+struct Completion {
+    /// Byte index in the input where the completed word starts
+    start: usize,
+    candidates: Vec<String>,
+    /// The candidate in the input, none before cycling
+    shown: Option<usize>,
+}
+
+#[derive(Resource, Default)]
+pub struct DevConsole {
+    input: String,
+    /// Position in the input, in characters
+    cursor: usize,
+    scrollback: Vec<ConsoleLine>,
+    /// Lines scrolled up from the newest one
+    scroll: usize,
+    history: Vec<String>,
+    /// The history entry being shown, none while typing a new line
+    history_index: Option<usize>,
+    /// The line that was being typed before browsing the history
+    draft: String,
+    completion: Option<Completion>,
+}
+
+impl DevConsole {
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The input split at the cursor
+    pub fn split_input(&self) -> (&str, &str) {
+        self.input.split_at(self.byte_index(self.cursor))
+    }
+
+    pub fn set_input(&mut self, input: impl Into<String>) {
+        self.input = input.into();
+        self.cursor = self.input.chars().count();
+        self.history_index = None;
+        self.completion = None;
+    }
+
+    /// Takes the input to be run and adds it to the history and scrollback
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input);
+        self.cursor = 0;
+        self.history_index = None;
+        self.completion = None;
+        self.draft.clear();
+        self.scroll = 0;
+
+        let trimmed = line.trim();
+        if !trimmed.is_empty() && self.history.last().map(String::as_str) != Some(trimmed) {
+            self.history.push(trimmed.to_string());
+
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+
+        self.print(ConsoleLine {
+            text: format!("> {}", line),
+            kind: ConsoleLineKind::Command,
+        });
+
+        line
+    }
+
+    pub fn print(&mut self, line: ConsoleLine) {
+        //Keep the view on the same lines while scrolled up
+        if self.scroll > 0 {
+            self.scroll += 1;
+        }
+
+        self.scrollback.push(line);
+
+        if self.scrollback.len() > MAX_SCROLLBACK {
+            self.scrollback.remove(0);
+        }
+        //Lines dropped off the top can't be scrolled to
+        self.scroll = self.scroll.min(self.scrollback.len().saturating_sub(1));
+    }
+
+    /// The last `count` lines of the scrollback at the current scroll position, oldest first
+    pub fn visible_lines(&self, count: usize) -> &[ConsoleLine] {
+        let end = self.scrollback.len().saturating_sub(self.scroll);
+        &self.scrollback[end.saturating_sub(count)..end]
+    }
+
+    pub fn scroll(&mut self, lines: isize, page: usize) {
+        let max = self.scrollback.len().saturating_sub(page);
+        self.scroll = self.scroll.saturating_add_signed(lines).min(max);
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        let index = self.byte_index(self.cursor);
+        self.input.insert_str(index, text);
+        self.cursor += text.chars().count();
+        self.completion = None;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.delete();
+        }
+    }
+
+    /// Removes the character after the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.input.chars().count() {
+            self.input.remove(self.byte_index(self.cursor));
+        }
+        self.completion = None;
+    }
+
+    pub fn move_cursor(&mut self, chars: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(chars)
+            .min(self.input.chars().count());
+        self.completion = None;
+    }
+
+    pub fn cursor_to_start(&mut self) {
+        self.cursor = 0;
+        self.completion = None;
+    }
+
+    pub fn cursor_to_end(&mut self) {
+        self.cursor = self.input.chars().count();
+        self.completion = None;
+    }
+
+    /// Replaces the input with an older command
+    pub fn history_previous(&mut self) {
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.input.clone();
+                self.history.len() - 1
+            }
+        };
+
+        self.show_history(Some(index));
+    }
+
+    /// Replaces the input with a newer command, or the line typed before browsing the history
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => self.show_history(Some(index + 1)),
+            Some(_) => self.show_history(None),
+            None => {}
+        }
+    }
+
+    fn show_history(&mut self, index: Option<usize>) {
+        self.input = match index {
+            Some(index) => self.history[index].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.cursor = self.input.chars().count();
+        self.history_index = index;
+        self.completion = None;
+    }
+
+    /// Completes the word starting at `start` and ending at the cursor, lists the candidates
+    /// if there is more than one
+    pub fn complete(&mut self, start: usize, candidates: Vec<String>) {
+        match candidates.as_slice() {
+            [] => {}
+            [candidate] => {
+                //Directories are likely to be completed further
+                let separator = if candidate.ends_with('/') { "" } else { " " };
+                self.replace_word(start, &format!("{}{}", candidate, separator));
+            }
+            _ => {
+                self.replace_word(start, longest_common_prefix(&candidates));
+                self.print(ConsoleLine {
+                    text: candidates.join("  "),
+                    kind: ConsoleLineKind::Info,
+                });
+                self.completion = Some(Completion {
+                    start,
+                    candidates,
+                    shown: None,
+                });
+            }
+        }
+    }
+
+    /// Shows the next candidate of an ambiguous completion, false if there is none
+    pub fn cycle_completion(&mut self) -> bool {
+        let Some(mut completion) = self.completion.take() else {
+            return false;
+        };
+
+        let shown = completion
+            .shown
+            .map_or(0, |shown| (shown + 1) % completion.candidates.len());
+        self.replace_word(completion.start, &completion.candidates[shown]);
+
+        completion.shown = Some(shown);
+        self.completion = Some(completion);
+        true
+    }
+
+    fn replace_word(&mut self, start: usize, word: &str) {
+        let end = self.byte_index(self.cursor);
+        self.input.replace_range(start..end, word);
+        self.cursor = self.input[..start + word.len()].chars().count();
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.input.len(), |(i, _)| i)
+    }
+
+    /// Replaces the history with the one in a file, one command per line
+    pub fn load_history(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let history = fs::read_to_string(path)?;
+
+        self.history = history
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let excess = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..excess);
+
+        Ok(())
+    }
+
+    pub fn save_history(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut history = self.history.join("\n");
+        history.push('\n');
+        fs::write(path, history)
+    }
+}
+
+pub(super) fn load_console_history(mut console: ResMut<DevConsole>) {
+    match console.load_history(HISTORY_FILE) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Could not load console history: {}", e),
+    }
+}
+
+/// Adds the output of commands to the scrollback, it is kept while the console is closed
+pub(super) fn collect_console_output(
+    mut output_reader: MessageReader<ConsoleOutput>,
+    mut console: ResMut<DevConsole>,
+) {
+    for ConsoleOutput(line) in output_reader.read() {
+        match line.kind {
+            ConsoleLineKind::Error => warn!("{}", line.text),
+            _ => info!("{}", line.text),
+        }
+
+        console.print(line.clone());
+    }
+}
@@ -1,7 +1,14 @@
+pub mod arguments;
 pub mod command_parser;
+pub mod completion;
+pub mod console;
+pub mod script;
+mod simulation;
+
+use std::path::PathBuf;
 
 use bevy::{
-    color::palettes::css::WHITE,
+    color::palettes::css::{GRAY, RED, WHITE},
     input::{
         ButtonState,
         keyboard::{Key, KeyboardInput},
@@ -9,10 +16,17 @@ use bevy::{
     prelude::*,
 };
 use command_parser::DevCommandList;
-use radix_trie::TrieCommon;
+use completion::complete_cmdline;
+use console::{
+    ConsoleLine, ConsoleLineKind, ConsoleOutput, DevConsole, HISTORY_FILE, collect_console_output,
+    load_console_history,
+};
+use script::{AUTOEXEC_FILE, ScriptDepth, StartupScripts, exec_args, run_startup_scripts};
 
 const CMDLINE_FONT_SIZE: f32 = 16.0;
 const CMDLINE_FONT: &str = "fonts/FiraMono-Regular.ttf";
+/// Lines of scrollback shown above the command line
+const SCROLLBACK_LINES: usize = 12;
 
 #[derive(States, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CmdlineState {
@@ -21,26 +35,85 @@ pub enum CmdlineState {
 }
 
 #[derive(Component)]
-struct DevCommandlineTextMarker;
+struct DevCommandlineMarker;
 
+/// Shows the scrollback line at this index from the top
 #[derive(Component)]
-struct DevCommandlineMarker;
+struct ScrollbackLine(usize);
+
+#[derive(Component)]
+enum InputSpan {
+    BeforeCursor,
+    Cursor,
+    AfterCursor,
+}
 
-pub struct DevCommandlinePlugin;
+pub struct DevCommandlinePlugin {
+    /// Script run at startup if the file exists
+    pub autoexec: Option<PathBuf>,
+    /// Scripts run at startup after the autoexec, in order
+    pub exec: Vec<PathBuf>,
+}
+
+impl DevCommandlinePlugin {
+    /// Also runs the scripts given to the program with `--exec <file>`
+    pub fn from_args() -> Self {
+        Self {
+            exec: exec_args(std::env::args().skip(1)),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for DevCommandlinePlugin {
+    fn default() -> Self {
+        Self {
+            autoexec: Some(PathBuf::from(AUTOEXEC_FILE)),
+            exec: Vec::new(),
+        }
+    }
+}
 
 impl Plugin for DevCommandlinePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.insert_state(CmdlineState::Closed);
+        app.init_resource::<DevConsole>();
+        app.init_resource::<ScriptDepth>();
+        app.insert_resource(StartupScripts {
+            autoexec: self.autoexec.clone(),
+            exec: self.exec.clone(),
+        });
+        app.add_message::<ConsoleOutput>();
+        app.add_systems(Startup, load_console_history);
+        //After the level is built, so scripts can change it
+        app.add_systems(PostStartup, run_startup_scripts);
         app.add_systems(
             Update,
             (
                 toggle_cmdline,
-                update_cmdline.run_if(in_state(CmdlineState::Open)),
-            ),
+                collect_console_output,
+                (update_cmdline, draw_cmdline)
+                    .chain()
+                    .run_if(in_state(CmdlineState::Open)),
+            )
+                .chain(),
         );
         app.add_systems(OnEnter(CmdlineState::Open), enter_cmdline);
         app.add_systems(OnExit(CmdlineState::Open), exit_cmdline);
     }
+
+    //The command list is often inserted after the plugin is added
+    fn finish(&self, app: &mut App) {
+        let world = app.world_mut();
+
+        if !world.contains_resource::<DevCommandList>() {
+            world.insert_resource(DevCommandList::new());
+        }
+
+        world.resource_scope(|world, mut dev_commands: Mut<DevCommandList>| {
+            dev_commands.add_builtin_commands(world)
+        });
+    }
 }
 
 fn toggle_cmdline(
@@ -57,37 +130,66 @@ fn toggle_cmdline(
 }
 
 fn enter_cmdline(mut commands: Commands, assets: Res<AssetServer>) {
+    let font = TextFont {
+        font_size: CMDLINE_FONT_SIZE,
+        font: assets.load(CMDLINE_FONT),
+        ..Default::default()
+    };
+
     commands
         .spawn((
-            Text::new("> "),
-            TextFont {
-                font_size: CMDLINE_FONT_SIZE,
-                font: assets.load(CMDLINE_FONT),
-                ..Default::default()
-            },
-            TextColor(WHITE.into()),
-            TextLayout {
-                justify: Justify::Left,
-                ..Default::default()
-            },
             Node {
                 position_type: PositionType::Absolute,
                 bottom: Val::Px(5.0),
                 left: Val::Px(5.0),
+                right: Val::Px(5.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(5.0)),
                 ..Default::default()
             },
+            BackgroundColor(Color::BLACK.with_alpha(0.6)),
             DevCommandlineMarker,
         ))
-        .with_child((
-            TextSpan::default(),
-            TextFont {
-                font_size: CMDLINE_FONT_SIZE,
-                font: assets.load(CMDLINE_FONT),
-                ..Default::default()
-            },
-            TextColor(WHITE.into()),
-            DevCommandlineTextMarker,
-        ));
+        .with_children(|parent| {
+            parent
+                .spawn((Text::default(), font.clone()))
+                .with_children(|log| {
+                    for i in 0..SCROLLBACK_LINES {
+                        log.spawn((TextSpan::default(), font.clone(), ScrollbackLine(i)));
+                    }
+                });
+
+            parent
+                .spawn((
+                    Text::new("> "),
+                    font.clone(),
+                    TextColor(WHITE.into()),
+                    TextLayout {
+                        justify: Justify::Left,
+                        ..Default::default()
+                    },
+                ))
+                .with_children(|line| {
+                    line.spawn((
+                        TextSpan::default(),
+                        font.clone(),
+                        TextColor(WHITE.into()),
+                        InputSpan::BeforeCursor,
+                    ));
+                    line.spawn((
+                        TextSpan::new("|"),
+                        font.clone(),
+                        TextColor(GRAY.into()),
+                        InputSpan::Cursor,
+                    ));
+                    line.spawn((
+                        TextSpan::default(),
+                        font.clone(),
+                        TextColor(WHITE.into()),
+                        InputSpan::AfterCursor,
+                    ));
+                });
+        });
 }
 
 fn exit_cmdline(
@@ -101,12 +203,10 @@ fn exit_cmdline(
 
 fn update_cmdline(
     mut ev_kb_input: MessageReader<KeyboardInput>,
-    mut cmdline_query: Query<&mut TextSpan, With<DevCommandlineTextMarker>>,
+    mut console: ResMut<DevConsole>,
     mut commands: Commands,
     dev_comands: Res<DevCommandList>,
 ) {
-    let mut text = cmdline_query.single_mut().unwrap();
-
     for event in ev_kb_input.read() {
         if event.state == ButtonState::Released {
             continue;
@@ -118,37 +218,80 @@ fn update_cmdline(
 
         match &event.logical_key {
             Key::Enter => {
-                match dev_comands.0.get_ancestor_value(&text.0) {
-                    Some(dev_cmd) => {
-                        let args = &text
-                            .0
-                            .strip_prefix(dev_cmd.prefix())
-                            .expect("Command text should always start with itself")
-                            .trim();
-
-                        dev_cmd.run(&mut commands, args);
-                    }
-                    None => {
-                        info!("Incorrect Command")
-                    }
-                };
-                text.0.clear();
-            }
-            Key::Tab => {
-                if let Some(subtrie) = dev_comands.0.get_raw_descendant(&text.0)
-                    && let Some(key) = subtrie.key()
+                let line = console.submit();
+
+                if !line.trim().is_empty()
+                    && let Err(error) = dev_comands.run_line(&mut commands, &line)
                 {
-                    text.0 = key.to_string();
+                    console.print(ConsoleLine {
+                        text: error,
+                        kind: ConsoleLineKind::Error,
+                    });
+                }
+
+                if let Err(e) = console.save_history(HISTORY_FILE) {
+                    warn!("Could not save console history: {}", e);
                 }
             }
-            Key::Backspace => {
-                text.0.pop();
-            }
-            Key::Space => {
-                text.0.push(' ');
+            Key::Tab => {
+                //The rest of the keys wait for the completion to be applied
+                commands.queue(complete_cmdline);
+                break;
             }
-            Key::Character(c) => text.0.push_str(c),
+            Key::ArrowUp => console.history_previous(),
+            Key::ArrowDown => console.history_next(),
+            Key::ArrowLeft => console.move_cursor(-1),
+            Key::ArrowRight => console.move_cursor(1),
+            Key::Home => console.cursor_to_start(),
+            Key::End => console.cursor_to_end(),
+            Key::PageUp => console.scroll(SCROLLBACK_LINES as isize, SCROLLBACK_LINES),
+            Key::PageDown => console.scroll(-(SCROLLBACK_LINES as isize), SCROLLBACK_LINES),
+            Key::Backspace => console.backspace(),
+            Key::Delete => console.delete(),
+            Key::Space => console.insert(" "),
+            Key::Character(c) => console.insert(c),
             _ => {}
         }
     }
 }
+
+fn draw_cmdline(
+    console: Res<DevConsole>,
+    new_cmdline_query: Query<(), Added<DevCommandlineMarker>>,
+    mut scrollback_query: Query<(&mut TextSpan, &mut TextColor, &ScrollbackLine)>,
+    mut input_query: Query<(&mut TextSpan, &InputSpan), Without<ScrollbackLine>>,
+) {
+    if !console.is_changed() && new_cmdline_query.is_empty() {
+        return;
+    }
+
+    let lines = console.visible_lines(SCROLLBACK_LINES);
+
+    for (mut span, mut color, ScrollbackLine(i)) in &mut scrollback_query {
+        let Some(line) = lines.get(*i) else {
+            span.0.clear();
+            continue;
+        };
+
+        span.0 = if *i == 0 {
+            line.text.clone()
+        } else {
+            format!("\n{}", line.text)
+        };
+        color.0 = match line.kind {
+            ConsoleLineKind::Command => GRAY.into(),
+            ConsoleLineKind::Info => WHITE.into(),
+            ConsoleLineKind::Error => RED.into(),
+        };
+    }
+
+    let (before, after) = console.split_input();
+
+    for (mut span, input_span) in &mut input_query {
+        match input_span {
+            InputSpan::BeforeCursor => span.0 = before.to_string(),
+            InputSpan::AfterCursor => span.0 = after.to_string(),
+            InputSpan::Cursor => {}
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Files of dev commands, run with ```exec <file>```
+//!
+//! Every line is run as if it was typed into the console, blank lines and lines
+//! starting with `#` or `//` are skipped. Errors are reported with the file and
+//! line number and don't stop the rest of the file from running.
+//!
+//! The [`DevCommandlinePlugin`](super::DevCommandlinePlugin) runs [`AUTOEXEC_FILE`]
+//! at startup if it exists, followed by every file given with `--exec <file>`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use super::{
+    command_parser::DevCommandList,
+    console::{ConsoleLine, ConsoleLineKind, ConsoleOutput},
+};
+
+pub const AUTOEXEC_FILE: &str = "autoexec.cfg";
+/// Scripts can exec other scripts, this stops a script from running itself forever
+const MAX_SCRIPT_DEPTH: usize = 8;
+
+/// Number of scripts currently running inside each other
+#[derive(Resource, Default)]
+pub(super) struct ScriptDepth(usize);
+
+#[derive(Resource)]
+pub(super) struct StartupScripts {
+    pub(super) autoexec: Option<PathBuf>,
+    pub(super) exec: Vec<PathBuf>,
+}
+
+/// The files given with `--exec <file>` or `--exec=<file>`
+pub fn exec_args(args: impl IntoIterator<Item = String>) -> Vec<PathBuf> {
+    let mut args = args.into_iter();
+    let mut files = Vec::new();
+
+    while let Some(arg) = args.next() {
+        if arg == "--exec" {
+            match args.next() {
+                Some(file) => files.push(PathBuf::from(file)),
+                None => warn!("--exec needs a file"),
+            }
+        } else if let Some(file) = arg.strip_prefix("--exec=") {
+            files.push(PathBuf::from(file));
+        }
+    }
+
+    files
+}
+
+pub(super) fn exec_command(
+    path: In<String>,
+    dev_commands: Res<DevCommandList>,
+    depth: Res<ScriptDepth>,
+    mut commands: Commands,
+) {
+    let path = path.0.trim_matches('"');
+
+    if path.is_empty() {
+        commands.write_message(ConsoleOutput::error("exec needs a file"));
+        return;
+    }
+
+    run_script(Path::new(path), &dev_commands, depth.0, &mut commands);
+}
+
+pub(super) fn run_startup_scripts(
+    scripts: Res<StartupScripts>,
+    dev_commands: Res<DevCommandList>,
+    depth: Res<ScriptDepth>,
+    mut commands: Commands,
+) {
+    if let Some(autoexec) = &scripts.autoexec
+        && autoexec.is_file()
+    {
+        run_script(autoexec, &dev_commands, depth.0, &mut commands);
+    }
+
+    for path in &scripts.exec {
+        run_script(path, &dev_commands, depth.0, &mut commands);
+    }
+}
+
+/// Queues every command of the script, output is queued along with them to keep it in order
+fn run_script(path: &Path, dev_commands: &DevCommandList, depth: usize, commands: &mut Commands) {
+    if depth >= MAX_SCRIPT_DEPTH {
+        commands.write_message(ConsoleOutput::error(format!(
+            "Scripts are nested too deep, not running {}",
+            path.display()
+        )));
+        return;
+    }
+
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(e) => {
+            commands.write_message(ConsoleOutput::error(format!(
+                "Could not read {}: {}",
+                path.display(),
+                e
+            )));
+            return;
+        }
+    };
+
+    commands.queue(|world: &mut World| world.resource_mut::<ScriptDepth>().0 += 1);
+
+    for (index, line) in script.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+
+        commands.write_message(ConsoleOutput(ConsoleLine {
+            text: format!("> {}", line),
+            kind: ConsoleLineKind::Command,
+        }));
+
+        if let Err(e) = dev_commands.run_line(commands, line) {
+            commands.write_message(ConsoleOutput::error(format!(
+                "{}:{}: {}",
+                path.display(),
+                index + 1,
+                e
+            )));
+        }
+    }
+
+    commands.queue(|world: &mut World| world.resource_mut::<ScriptDepth>().0 -= 1);
+}
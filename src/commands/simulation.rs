@@ -0,0 +1,232 @@
+//! Dev commands controlling the simulation
+//!
+//! Pausing and stepping need the [`PhysicsPlugin`](crate::physics::PhysicsPlugin)
+//! to run in [`SimulationState::Running`].
+
+use bevy::prelude::*;
+
+use super::console::ConsoleOutput;
+use crate::{
+    level_builder::{LevelBuilder, LevelReplacer},
+    physics::{
+        Frozen, PendingSteps, PhysicsMaterial, PhysicsSettings, PhysicsTransform, PhysicsVelocity,
+        SimulationState, integrator::Body,
+    },
+};
+
+const NO_SIMULATION_STATE: &str = "The simulation isn't run in a SimulationState";
+
+pub(super) fn pause(
+    _: In<()>,
+    state: Option<Res<State<SimulationState>>>,
+    next_state: Option<ResMut<NextState<SimulationState>>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    set_simulation_state(SimulationState::Paused, state, next_state, &mut output);
+}
+
+pub(super) fn resume(
+    _: In<()>,
+    state: Option<Res<State<SimulationState>>>,
+    next_state: Option<ResMut<NextState<SimulationState>>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    set_simulation_state(SimulationState::Running, state, next_state, &mut output);
+}
+
+fn set_simulation_state(
+    target: SimulationState,
+    state: Option<Res<State<SimulationState>>>,
+    next_state: Option<ResMut<NextState<SimulationState>>>,
+    output: &mut MessageWriter<ConsoleOutput>,
+) {
+    let (Some(state), Some(mut next_state)) = (state, next_state) else {
+        output.write(ConsoleOutput::error(NO_SIMULATION_STATE));
+        return;
+    };
+
+    if *state.get() == target {
+        output.write(ConsoleOutput::info(format!(
+            "Simulation is already {:?}",
+            target
+        )));
+        return;
+    }
+
+    next_state.set(target);
+    output.write(ConsoleOutput::info(match target {
+        SimulationState::Running => "Simulation resumed",
+        SimulationState::Paused => "Simulation paused",
+    }));
+}
+
+/// Pauses the simulation if it is running, the steps start once it is paused
+pub(super) fn step(
+    steps: In<Option<u32>>,
+    state: Option<Res<State<SimulationState>>>,
+    next_state: Option<ResMut<NextState<SimulationState>>>,
+    pending_steps: Option<ResMut<PendingSteps>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let (Some(state), Some(mut next_state), Some(mut pending_steps)) =
+        (state, next_state, pending_steps)
+    else {
+        output.write(ConsoleOutput::error(NO_SIMULATION_STATE));
+        return;
+    };
+
+    if *state.get() == SimulationState::Running {
+        next_state.set(SimulationState::Paused);
+        output.write(ConsoleOutput::info("Simulation paused"));
+    }
+
+    let steps = steps.0.unwrap_or(1);
+    pending_steps.0 = pending_steps.0.saturating_add(steps);
+    output.write(ConsoleOutput::info(format!(
+        "Stepping {} fixed updates",
+        steps
+    )));
+}
+
+/// Despawns every planet and builds the level the app started with, or the last one opened
+///
+/// Pending steps are dropped and energy drift is measured from the rebuilt level
+pub(super) fn reset(
+    _: In<()>,
+    level: Option<Res<LevelBuilder>>,
+    mut level_replacer: LevelReplacer,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let Some(level) = level else {
+        output.write(ConsoleOutput::error("There is no level to reset to"));
+        return;
+    };
+
+    level_replacer.replace(level.clone());
+
+    output.write(ConsoleOutput::info("Level reset"));
+}
+
+pub(super) fn timestep(
+    hz: In<f64>,
+    mut time: ResMut<Time<Fixed>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    if !(hz.0.is_finite() && hz.0 > 0.0) {
+        output.write(ConsoleOutput::error("The rate has to be above 0"));
+        return;
+    }
+
+    time.set_timestep_hz(hz.0);
+    output.write(ConsoleOutput::info(format!(
+        "Fixed timestep set to {} hz",
+        hz.0
+    )));
+}
+
+/// Prints the gravitational constant when not given one
+pub(super) fn gravity(
+    gravitational_constant: In<Option<f32>>,
+    physics_settings: Option<ResMut<PhysicsSettings>>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let Some(mut physics_settings) = physics_settings else {
+        output.write(ConsoleOutput::error(
+            "Physics are not running, there is no gravitational constant",
+        ));
+        return;
+    };
+
+    match gravitational_constant.0 {
+        //0 turns gravity off, a negative constant would make it push bodies apart
+        Some(gravitational_constant)
+            if !(gravitational_constant.is_finite() && gravitational_constant >= 0.0) =>
+        {
+            output.write(ConsoleOutput::error(
+                "The gravitational constant has to be 0 or above",
+            ));
+        }
+        Some(gravitational_constant) => {
+            physics_settings.gravitational_constant = gravitational_constant;
+            output.write(ConsoleOutput::info(format!(
+                "Gravitational constant set to: {}",
+                gravitational_constant
+            )));
+        }
+        None => {
+            output.write(ConsoleOutput::info(format!(
+                "Gravitational constant: {}",
+                physics_settings.gravitational_constant
+            )));
+        }
+    }
+}
+
+/// Prints the mass, position and velocity of a planet
+pub(super) fn planet(
+    entity: In<Entity>,
+    planet_query: Query<(
+        &PhysicsMaterial,
+        &PhysicsTransform,
+        Option<&PhysicsVelocity>,
+    )>,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let Ok((material, transform, velocity)) = planet_query.get(entity.0) else {
+        output.write(ConsoleOutput::error(format!(
+            "{} is not a planet",
+            entity.0
+        )));
+        return;
+    };
+
+    output.write(ConsoleOutput::info(format!(
+        "Planet {}: mass {}, position {}",
+        entity.0, material.mass, transform.location
+    )));
+    output.write(ConsoleOutput::info(match velocity {
+        Some(velocity) => format!("    velocity {}", velocity.velocity),
+        None => "    static".to_string(),
+    }));
+}
+
+/// Puts frozen planets back into the simulation, all of them when not given one
+///
+/// Planets whose state still isn't finite stay frozen
+pub(super) fn unfreeze(
+    entity: In<Option<Entity>>,
+    frozen_query: Query<
+        (
+            Entity,
+            &PhysicsMaterial,
+            &PhysicsTransform,
+            Option<&PhysicsVelocity>,
+        ),
+        With<Frozen>,
+    >,
+    mut commands: Commands,
+    mut output: MessageWriter<ConsoleOutput>,
+) {
+    let frozen: Vec<_> = match entity.0 {
+        Some(entity) => match frozen_query.get(entity) {
+            Ok(frozen) => vec![frozen],
+            Err(_) => {
+                output.write(ConsoleOutput::error(format!("{} is not frozen", entity)));
+                return;
+            }
+        },
+        None => frozen_query.iter().collect(),
+    };
+
+    for (entity, material, transform, velocity) in frozen {
+        if Body::from_components(material, transform, velocity).is_finite() {
+            commands.entity(entity).remove::<Frozen>();
+            output.write(ConsoleOutput::info(format!("Unfroze {}", entity)));
+        } else {
+            output.write(ConsoleOutput::error(format!(
+                "{} still has a non-finite state",
+                entity
+            )));
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Copying and pasting planets
+//!
+//! Ctrl+C copies the selected planets, Ctrl+V pastes them centered on the
+//! cursor keeping their relative positions and velocities, Ctrl+D duplicates
+//! the selection without touching the clipboard. The clipboard can also be
+//! turned into level file text and back, to share snippets between levels.
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui::Ui};
+
+use super::{
+    history::EditHistory,
+    selection::{DUPLICATE_OFFSET, Selection, SnapshotData, snapshot, spawn_planets},
+};
+use crate::level_builder::{LevelBuilder, LevelFileError, PlanetBuilder};
+
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    planets: Vec<PlanetBuilder>,
+    /// Level file text typed or pasted into the clipboard menu
+    text: String,
+}
+
+impl Clipboard {
+    pub fn planets(&self) -> &[PlanetBuilder] {
+        &self.planets
+    }
+
+    /// The clipboard as the text of a level file
+    pub fn to_ron(&self) -> Result<String, LevelFileError> {
+        self.planets
+            .iter()
+            .cloned()
+            .fold(LevelBuilder::default(), LevelBuilder::add_planet)
+            .to_ron()
+    }
+
+    /// Replaces the clipboard with the planets of a level file
+    pub fn set_from_ron(&mut self, text: &str) -> Result<(), LevelFileError> {
+        self.planets = LevelBuilder::from_ron(text)?.planets().collect();
+        Ok(())
+    }
+
+    /// The planets moved so their center is at `center`
+    fn planets_at(&self, center: Vec2) -> Vec<PlanetBuilder> {
+        let Some(offset) = self.center().map(|c| center - c) else {
+            return Vec::new();
+        };
+
+        self.planets
+            .iter()
+            .map(|planet| PlanetBuilder {
+                position: planet.position + offset,
+                ..planet.clone()
+            })
+            .collect()
+    }
+
+    fn center(&self) -> Option<Vec2> {
+        (!self.planets.is_empty()).then(|| {
+            self.planets.iter().map(|p| p.position).sum::<Vec2>() / self.planets.len() as f32
+        })
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn clipboard_keybindings<T>(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_context: EguiContexts,
+    camera_query: Query<(&Camera, &GlobalTransform), With<T>>,
+    window_query: Query<&Window>,
+    planet_query: Query<SnapshotData>,
+    mut clipboard: ResMut<Clipboard>,
+    mut selection: ResMut<Selection>,
+    mut history: ResMut<EditHistory>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) where
+    T: Component,
+{
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    //Text fields have their own clipboard
+    if egui_context
+        .ctx_mut()
+        .is_ok_and(|ctx| ctx.wants_keyboard_input())
+    {
+        return;
+    }
+
+    let selected = || -> Vec<PlanetBuilder> {
+        snapshot(selection.entities(), &planet_query, &materials)
+            .into_iter()
+            .map(|(_, planet)| planet)
+            .collect()
+    };
+
+    let pasted = if keys.just_pressed(KeyCode::KeyC) {
+        let planets = selected();
+
+        if !planets.is_empty() {
+            clipboard.planets = planets;
+        }
+
+        return;
+    } else if keys.just_pressed(KeyCode::KeyD) {
+        selected()
+            .into_iter()
+            .map(|planet| PlanetBuilder {
+                position: planet.position + DUPLICATE_OFFSET,
+                ..planet
+            })
+            .collect()
+    } else if keys.just_pressed(KeyCode::KeyV) {
+        let Ok((camera, camera_transform)) = camera_query.single() else {
+            return;
+        };
+        let Some(cursor) = window_query
+            .single()
+            .ok()
+            .and_then(|w| w.cursor_position())
+            .and_then(|c| camera.viewport_to_world_2d(camera_transform, c).ok())
+        else {
+            return;
+        };
+
+        clipboard.planets_at(cursor)
+    } else {
+        return;
+    };
+
+    if pasted.is_empty() {
+        return;
+    }
+
+    let pasted = spawn_planets(
+        pasted,
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &mut history,
+    );
+    selection.set(pasted);
+}
+
+/// Shares the clipboard as level file text
+pub(super) fn clipboard_menu(ui: &mut Ui, clipboard: &mut Clipboard) {
+    ui.label(format!("{} planets copied", clipboard.planets.len()));
+
+    if ui.button("Copy as Level Text").clicked() {
+        match clipboard.to_ron() {
+            Ok(text) => ui.ctx().copy_text(text),
+            Err(e) => error!("Could not write clipboard as level text: {}", e),
+        }
+        ui.close();
+    }
+
+    ui.separator();
+    ui.label("Level text");
+    ui.text_edit_multiline(&mut clipboard.text);
+
+    if ui.button("Load into Clipboard").clicked() {
+        let text = std::mem::take(&mut clipboard.text);
+
+        if let Err(e) = clipboard.set_from_ron(&text) {
+            error!("Could not read level text: {}", e);
+            clipboard.text = text;
+        }
+    }
+}
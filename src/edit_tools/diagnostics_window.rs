@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    EguiContexts,
+    egui::{self, Grid, Ui},
+};
+
+use crate::physics::diagnostics::PhysicsDiagnostics;
+
+pub(super) fn draw_diagnostics_window(
+    mut context: EguiContexts,
+    diagnostics: Option<ResMut<PhysicsDiagnostics>>,
+) {
+    let Some(mut diagnostics) = diagnostics else {
+        return;
+    };
+
+    egui::Window::new("Physics Diagnostics")
+        .default_open(false)
+        .resizable([false; 2])
+        .show(
+            context.ctx_mut().expect("Egui context should exist"),
+            |ui| {
+                Grid::new("diagnostics").show(ui, |ui| {
+                    value_row(
+                        ui,
+                        "Kinetic energy",
+                        format!("{:.3}", diagnostics.kinetic_energy),
+                    );
+                    value_row(
+                        ui,
+                        "Potential energy",
+                        format!("{:.3}", diagnostics.potential_energy),
+                    );
+                    value_row(
+                        ui,
+                        "Total energy",
+                        format!(
+                            "{:.3} ({:+.2e})",
+                            diagnostics.total_energy(),
+                            diagnostics.energy_drift()
+                        ),
+                    );
+                    value_row(
+                        ui,
+                        "Linear momentum",
+                        format!(
+                            "{:.3} ({:+.2e})",
+                            diagnostics.linear_momentum,
+                            diagnostics.momentum_drift()
+                        ),
+                    );
+                    value_row(
+                        ui,
+                        "Angular momentum",
+                        format!(
+                            "{:.3} ({:+.2e})",
+                            diagnostics.angular_momentum,
+                            diagnostics.angular_momentum_drift()
+                        ),
+                    );
+                    value_row(
+                        ui,
+                        "Center of mass",
+                        format!("{:.3}", diagnostics.center_of_mass),
+                    );
+                });
+
+                if ui.button("Reset Baseline").clicked() {
+                    diagnostics.reset_baseline();
+                }
+            },
+        );
+}
+
+fn value_row(ui: &mut Ui, label: &str, value: String) {
+    ui.label(label);
+    ui.label(value);
+    ui.end_row();
+}
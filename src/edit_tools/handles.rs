@@ -0,0 +1,377 @@
+//! Direct manipulation of planets in the world view
+//!
+//! Left dragging a planet moves it, left dragging the tip of the velocity arrow
+//! of a planet with an open editor window sets its velocity. Holding Shift snaps
+//! to the grid, holding Alt snaps the velocity direction to fixed angles.
+
+use std::f32::consts::PI;
+
+use bevy::{
+    color::palettes::css::YELLOW,
+    picking::{hover::HoverMap, pointer::PointerId},
+    prelude::*,
+};
+use bevy_egui::EguiContexts;
+
+use super::{
+    history::{ComponentChange, EditHistory},
+    window::{EditorWindow, PlanetCreator},
+};
+use crate::{
+    level_builder::calculate_radius,
+    physics::{PhysicsTransform, PhysicsVelocity},
+};
+
+/// The velocity arrow ends where the body would be after this many seconds
+const VELOCITY_HANDLE_SCALE: f32 = 1.0;
+/// In screen pixels
+const HANDLE_RADIUS: f32 = 8.0;
+const GRID_SIZE: f32 = 10.0;
+const ANGLE_STEP: f32 = PI / 12.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum DragTarget {
+    Planet(Entity),
+    /// The preview of the Planet Creator
+    Creator,
+}
+
+#[derive(Clone, Copy)]
+enum DragKind {
+    /// Keeps the point of the planet that was grabbed under the cursor
+    Move {
+        offset: Vec2,
+    },
+    Velocity,
+}
+
+struct ActiveDrag {
+    target: DragTarget,
+    kind: DragKind,
+    start_transform: Option<PhysicsTransform>,
+    start_velocity: Option<PhysicsVelocity>,
+    moved: bool,
+}
+
+#[derive(Resource, Default)]
+pub(super) struct HandleDrag {
+    active: Option<ActiveDrag>,
+}
+
+impl HandleDrag {
+    /// True while something is being dragged, clicks that end a drag shouldn't open windows
+    pub(super) fn is_dragging(&self) -> bool {
+        self.active.as_ref().is_some_and(|a| a.moved)
+    }
+
+    /// True from the moment a planet or handle is grabbed, even before it moves
+    pub(super) fn has_target(&self) -> bool {
+        self.active.is_some()
+    }
+}
+
+pub(super) fn draw_velocity_handles<T>(
+    mut gizmos: Gizmos,
+    camera_query: Query<&Projection, With<T>>,
+    planet_query: Query<(&PhysicsTransform, &PhysicsVelocity, &EditorWindow)>,
+    creator: Res<PlanetCreator>,
+) where
+    T: Component,
+{
+    let scale = camera_scale(camera_query.single().ok());
+
+    let creator_planet = creator
+        .planet
+        .as_ref()
+        .filter(|_| creator.is_open)
+        .and_then(|p| Some((p.position, p.velocity?)));
+
+    let planets = planet_query
+        .iter()
+        .filter(|(.., window)| window.get())
+        .map(|(transform, velocity, _)| (transform.location(), velocity.velocity()));
+
+    for (position, velocity) in planets.chain(creator_planet) {
+        let tip = velocity_handle(position, velocity);
+
+        gizmos.arrow_2d(position, tip, YELLOW);
+        gizmos.circle_2d(tip, HANDLE_RADIUS * scale, YELLOW);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn drag_handles<T>(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera_query: Query<(&Camera, &GlobalTransform, &Projection), With<T>>,
+    window_query: Query<&Window>,
+    mut egui_context: EguiContexts,
+    hover_map: Res<HoverMap>,
+    mut planet_query: Query<(
+        Entity,
+        &mut PhysicsTransform,
+        Option<&mut PhysicsVelocity>,
+        &EditorWindow,
+    )>,
+    mut creator: ResMut<PlanetCreator>,
+    mut drag: ResMut<HandleDrag>,
+    mut history: ResMut<EditHistory>,
+) where
+    T: Component,
+{
+    if mouse_buttons.just_released(MouseButton::Left) {
+        if let Some(active) = drag.active.take()
+            && active.moved
+            && let DragTarget::Planet(entity) = active.target
+        {
+            record_drag(&active, entity, &planet_query, &mut history);
+        }
+
+        return;
+    }
+
+    let Ok((camera, camera_transform, projection)) = camera_query.single() else {
+        return;
+    };
+    let Some(cursor) = window_query
+        .single()
+        .ok()
+        .and_then(|w| w.cursor_position())
+        .and_then(|c| camera.viewport_to_world_2d(camera_transform, c).ok())
+    else {
+        return;
+    };
+
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        let over_egui = egui_context
+            .ctx_mut()
+            .is_ok_and(|ctx| ctx.is_pointer_over_area());
+
+        if !over_egui {
+            drag.active = start_drag(
+                cursor,
+                camera_scale(Some(projection)),
+                &hover_map,
+                &planet_query,
+                &creator,
+            );
+        }
+
+        return;
+    }
+
+    let Some(active) = &mut drag.active else {
+        return;
+    };
+
+    let grid = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let angle = keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+
+    let position = match active.target {
+        DragTarget::Planet(entity) => planet_query.get(entity).ok().map(|p| p.1.location()),
+        DragTarget::Creator => creator.planet.as_ref().map(|p| p.position),
+    };
+    let Some(position) = position else {
+        //The planet was removed mid drag
+        drag.active = None;
+        return;
+    };
+
+    let (new_position, new_velocity) = match active.kind {
+        DragKind::Move { offset } => (Some(snap_position(cursor + offset, grid)), None),
+        DragKind::Velocity => (
+            None,
+            Some(snap_velocity(
+                (cursor - position) / VELOCITY_HANDLE_SCALE,
+                grid,
+                angle,
+            )),
+        ),
+    };
+
+    match active.target {
+        DragTarget::Planet(entity) => {
+            let Ok((_, mut transform, velocity, _)) = planet_query.get_mut(entity) else {
+                return;
+            };
+
+            if let Some(new_position) = new_position {
+                transform.location = new_position;
+            }
+            if let Some(new_velocity) = new_velocity
+                && let Some(mut velocity) = velocity
+            {
+                velocity.velocity = new_velocity;
+            }
+        }
+        DragTarget::Creator => {
+            let Some(planet) = creator.planet.as_mut() else {
+                return;
+            };
+
+            if let Some(new_position) = new_position {
+                planet.position = new_position;
+            }
+            if new_velocity.is_some() {
+                planet.velocity = new_velocity;
+            }
+        }
+    }
+
+    active.moved = true;
+}
+
+/// Velocity handles take priority over bodies so the arrow of a big planet can be grabbed
+fn start_drag(
+    cursor: Vec2,
+    scale: f32,
+    hover_map: &HoverMap,
+    planet_query: &Query<(
+        Entity,
+        &mut PhysicsTransform,
+        Option<&mut PhysicsVelocity>,
+        &EditorWindow,
+    )>,
+    creator: &PlanetCreator,
+) -> Option<ActiveDrag> {
+    let creator_planet = creator.planet.as_ref().filter(|_| creator.is_open);
+    let on_handle = |position: Vec2, velocity: Vec2| {
+        velocity_handle(position, velocity).distance(cursor) <= HANDLE_RADIUS * scale
+    };
+
+    let drag = |target, kind, transform, velocity| ActiveDrag {
+        target,
+        kind,
+        start_transform: transform,
+        start_velocity: velocity,
+        moved: false,
+    };
+
+    if let Some(planet) = creator_planet
+        && let Some(velocity) = planet.velocity
+        && on_handle(planet.position, velocity)
+    {
+        return Some(drag(DragTarget::Creator, DragKind::Velocity, None, None));
+    }
+
+    for (entity, transform, velocity, window) in planet_query.iter() {
+        if let Some(velocity) = velocity
+            && window.get()
+            && on_handle(transform.location(), velocity.velocity())
+        {
+            return Some(drag(
+                DragTarget::Planet(entity),
+                DragKind::Velocity,
+                None,
+                Some(*velocity),
+            ));
+        }
+    }
+
+    if let Some(planet) = creator_planet {
+        let radius = planet.radius.unwrap_or(calculate_radius(planet.mass));
+
+        if planet.position.distance(cursor) <= radius {
+            return Some(drag(
+                DragTarget::Creator,
+                DragKind::Move {
+                    offset: planet.position - cursor,
+                },
+                None,
+                None,
+            ));
+        }
+    }
+
+    //Bodies under the cursor come from the physics picking backend
+    let hovered = hover_map.get(&PointerId::Mouse)?;
+
+    planet_query
+        .iter()
+        .find(|(entity, ..)| hovered.contains_key(entity))
+        .map(|(entity, transform, ..)| {
+            drag(
+                DragTarget::Planet(entity),
+                DragKind::Move {
+                    offset: transform.location() - cursor,
+                },
+                Some(*transform),
+                None,
+            )
+        })
+}
+
+/// Turns a finished drag into a single undo step
+fn record_drag(
+    active: &ActiveDrag,
+    entity: Entity,
+    planet_query: &Query<(
+        Entity,
+        &mut PhysicsTransform,
+        Option<&mut PhysicsVelocity>,
+        &EditorWindow,
+    )>,
+    history: &mut EditHistory,
+) {
+    let Ok((_, transform, velocity, _)) = planet_query.get(entity) else {
+        return;
+    };
+
+    if let Some(before) = active.start_transform {
+        history.record(
+            ComponentChange {
+                entity,
+                before,
+                after: *transform,
+            },
+            false,
+        );
+    }
+    if let Some(before) = active.start_velocity
+        && let Some(velocity) = velocity
+    {
+        history.record(
+            ComponentChange {
+                entity,
+                before,
+                after: *velocity,
+            },
+            false,
+        );
+    }
+}
+
+fn velocity_handle(position: Vec2, velocity: Vec2) -> Vec2 {
+    position + velocity * VELOCITY_HANDLE_SCALE
+}
+
+fn snap_position(position: Vec2, grid: bool) -> Vec2 {
+    if grid {
+        (position / GRID_SIZE).round() * GRID_SIZE
+    } else {
+        position
+    }
+}
+
+/// Grid snapping rounds the speed, angle snapping the direction
+fn snap_velocity(velocity: Vec2, grid: bool, angle: bool) -> Vec2 {
+    let mut speed = velocity.length();
+    let mut direction = velocity.to_angle();
+
+    if grid {
+        speed = (speed / GRID_SIZE).round() * GRID_SIZE;
+    }
+    if angle {
+        direction = (direction / ANGLE_STEP).round() * ANGLE_STEP;
+    }
+
+    Vec2::from_angle(direction) * speed
+}
+
+/// How many world units one screen pixel is
+pub(super) fn camera_scale(projection: Option<&Projection>) -> f32 {
+    match projection {
+        Some(Projection::Orthographic(projection)) => projection.scale,
+        _ => 1.0,
+    }
+}
@@ -0,0 +1,510 @@
+//! Undo and redo for the editor
+//!
+//! Every editor operation is recorded as an [`EditCommand`] which knows how to
+//! revert and reapply itself. Planets that get despawned and respawned by
+//! undoing come back as new entities, so the history remaps the stale entity in
+//! every recorded command.
+
+use std::any::Any;
+
+use bevy::{prelude::*, reflect::PartialReflect};
+use bevy_egui::EguiContexts;
+
+use crate::{graphics::trails::Trail, level_builder::PlanetBuilder, physics::PhysicsVelocity};
+
+/// How many commands are kept, the oldest are forgotten first
+const MAX_HISTORY: usize = 256;
+
+/// Everything a command may need to revert or reapply itself
+pub struct EditContext<'a, 'w, 's> {
+    pub commands: &'a mut Commands<'w, 's>,
+    pub meshes: &'a mut Assets<Mesh>,
+    pub materials: &'a mut Assets<ColorMaterial>,
+    respawned: Vec<(Entity, Entity)>,
+}
+
+impl EditContext<'_, '_, '_> {
+    /// Tells the history that an entity was recreated under a new id
+    pub fn respawned(&mut self, from: Entity, to: Entity) {
+        self.respawned.push((from, to));
+    }
+}
+
+/// A reversible editor operation
+pub trait EditCommand: Any + Send + Sync {
+    fn undo(&mut self, context: &mut EditContext);
+
+    fn redo(&mut self, context: &mut EditContext);
+
+    /// Replaces every reference to an entity which was respawned
+    fn remap_entity(&mut self, from: Entity, to: Entity);
+
+    /// Whether [`merge`](EditCommand::merge) would succeed, without changing anything
+    fn can_merge(&self, _next: &dyn EditCommand) -> bool {
+        false
+    }
+
+    /// Tries to fold a command recorded right after this one into it, used to
+    /// turn a continuous drag into a single undo step
+    fn merge(&mut self, _next: &dyn EditCommand) -> bool {
+        false
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo_stack: Vec<Box<dyn EditCommand>>,
+    redo_stack: Vec<Box<dyn EditCommand>>,
+    /// Set while a drag is ongoing, consecutive edits of the same value get merged
+    coalescing: bool,
+}
+
+impl EditHistory {
+    /// Records a command which has already been applied
+    ///
+    /// `continuous` should be set when the edit is part of an ongoing drag
+    pub fn record(&mut self, command: impl EditCommand, continuous: bool) {
+        self.redo_stack.clear();
+
+        if continuous
+            && self.coalescing
+            && let Some(last) = self.undo_stack.last_mut()
+            && last.merge(&command)
+        {
+            return;
+        }
+
+        self.undo_stack.push(Box::new(command));
+        self.coalescing = continuous;
+
+        if self.undo_stack.len() > MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    /// The next edit starts a new undo step even if it is continuous
+    pub fn stop_coalescing(&mut self) {
+        self.coalescing = false;
+    }
+
+    pub fn undo(&mut self, context: &mut EditContext) {
+        let Some(mut command) = self.undo_stack.pop() else {
+            return;
+        };
+
+        command.undo(context);
+        self.remap_respawned(command.as_mut(), context);
+        self.redo_stack.push(command);
+        self.coalescing = false;
+    }
+
+    pub fn redo(&mut self, context: &mut EditContext) {
+        let Some(mut command) = self.redo_stack.pop() else {
+            return;
+        };
+
+        command.redo(context);
+        self.remap_respawned(command.as_mut(), context);
+        self.undo_stack.push(command);
+        self.coalescing = false;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Forgets everything, for when the edited entities are gone
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.coalescing = false;
+    }
+
+    fn remap_respawned(&mut self, command: &mut dyn EditCommand, context: &mut EditContext) {
+        for (from, to) in context.respawned.drain(..) {
+            command.remap_entity(from, to);
+
+            for other in self.undo_stack.iter_mut().chain(self.redo_stack.iter_mut()) {
+                other.remap_entity(from, to);
+            }
+        }
+    }
+}
+
+/// A component was replaced with a new value
+pub struct ComponentChange<C: Component + Clone> {
+    pub entity: Entity,
+    pub before: C,
+    pub after: C,
+}
+
+impl<C: Component + Clone> EditCommand for ComponentChange<C> {
+    fn undo(&mut self, context: &mut EditContext) {
+        context
+            .commands
+            .entity(self.entity)
+            .try_insert(self.before.clone());
+    }
+
+    fn redo(&mut self, context: &mut EditContext) {
+        context
+            .commands
+            .entity(self.entity)
+            .try_insert(self.after.clone());
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+
+    fn can_merge(&self, next: &dyn EditCommand) -> bool {
+        (next as &dyn Any)
+            .downcast_ref::<Self>()
+            .is_some_and(|next| next.entity == self.entity)
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match (next as &dyn Any).downcast_ref::<Self>() {
+            Some(next) if self.can_merge(next) => {
+                self.after = next.after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The color of a planet's [`ColorMaterial`] was changed
+pub struct ColorChange {
+    pub entity: Entity,
+    pub before: Color,
+    pub after: Color,
+}
+
+impl ColorChange {
+    fn set_color(&self, context: &mut EditContext, color: Color) {
+        let entity = self.entity;
+
+        context.commands.queue(move |world: &mut World| {
+            let Some(handle) = world
+                .get::<MeshMaterial2d<ColorMaterial>>(entity)
+                .map(|m| m.0.clone())
+            else {
+                return;
+            };
+
+            if let Some(material) = world
+                .resource_mut::<Assets<ColorMaterial>>()
+                .get_mut(&handle)
+            {
+                material.color = color;
+            }
+        });
+    }
+}
+
+impl EditCommand for ColorChange {
+    fn undo(&mut self, context: &mut EditContext) {
+        self.set_color(context, self.before);
+    }
+
+    fn redo(&mut self, context: &mut EditContext) {
+        self.set_color(context, self.after);
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+
+    fn can_merge(&self, next: &dyn EditCommand) -> bool {
+        (next as &dyn Any)
+            .downcast_ref::<Self>()
+            .is_some_and(|next| next.entity == self.entity)
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match (next as &dyn Any).downcast_ref::<Self>() {
+            Some(next) if self.can_merge(next) => {
+                self.after = next.after;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A planet was created, or removed when `despawned` is set
+pub struct PlanetExistence {
+    pub entity: Entity,
+    pub planet: PlanetBuilder,
+    pub despawned: bool,
+}
+
+impl PlanetExistence {
+    pub fn spawned(entity: Entity, planet: PlanetBuilder) -> Self {
+        Self {
+            entity,
+            planet,
+            despawned: false,
+        }
+    }
+
+    pub fn despawned(entity: Entity, planet: PlanetBuilder) -> Self {
+        Self {
+            entity,
+            planet,
+            despawned: true,
+        }
+    }
+
+    fn spawn(&mut self, context: &mut EditContext) {
+        let entity = self.planet.clone().non_negative().build().build(
+            context.commands,
+            context.meshes,
+            context.materials,
+        );
+
+        context.respawned(self.entity, entity);
+    }
+
+    fn despawn(&self, context: &mut EditContext) {
+        context.commands.entity(self.entity).try_despawn();
+    }
+}
+
+impl EditCommand for PlanetExistence {
+    fn undo(&mut self, context: &mut EditContext) {
+        if self.despawned {
+            self.spawn(context);
+        } else {
+            self.despawn(context);
+        }
+    }
+
+    fn redo(&mut self, context: &mut EditContext) {
+        if self.despawned {
+            self.despawn(context);
+        } else {
+            self.spawn(context);
+        }
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+}
+
+/// A component was edited through reflection by the fallback inspector
+pub struct ReflectChange {
+    pub entity: Entity,
+    pub component: ReflectComponent,
+    pub before: Box<dyn PartialReflect>,
+    pub after: Box<dyn PartialReflect>,
+}
+
+impl ReflectChange {
+    fn set_value(&self, context: &mut EditContext, value: &dyn PartialReflect) {
+        let entity = self.entity;
+        let component = self.component.clone();
+        let value = value.to_dynamic();
+
+        context.commands.queue(move |world: &mut World| {
+            let Ok(mut entity) = world.get_entity_mut(entity) else {
+                return;
+            };
+
+            if let Some(mut reflected) = component.reflect_mut(&mut entity) {
+                reflected.apply(value.as_ref());
+            }
+        });
+    }
+}
+
+impl EditCommand for ReflectChange {
+    fn undo(&mut self, context: &mut EditContext) {
+        self.set_value(context, self.before.as_ref());
+    }
+
+    fn redo(&mut self, context: &mut EditContext) {
+        self.set_value(context, self.after.as_ref());
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+
+    fn can_merge(&self, next: &dyn EditCommand) -> bool {
+        (next as &dyn Any)
+            .downcast_ref::<Self>()
+            .is_some_and(|next| {
+                next.entity == self.entity
+                    && next.before.reflect_type_path() == self.before.reflect_type_path()
+            })
+    }
+
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match (next as &dyn Any).downcast_ref::<Self>() {
+            Some(next) if self.can_merge(next) => {
+                self.after = next.after.to_dynamic();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A planet was made dynamic, or static when `made_dynamic` is unset
+pub struct DynamicChange {
+    pub entity: Entity,
+    /// The velocity the planet has while it is dynamic
+    pub velocity: PhysicsVelocity,
+    pub made_dynamic: bool,
+}
+
+impl DynamicChange {
+    fn set_dynamic(&self, context: &mut EditContext, dynamic: bool) {
+        let mut entity = context.commands.entity(self.entity);
+
+        if dynamic {
+            entity.try_insert((self.velocity, Trail::default()));
+        } else {
+            entity.try_remove::<(PhysicsVelocity, Trail)>();
+        }
+    }
+}
+
+impl EditCommand for DynamicChange {
+    fn undo(&mut self, context: &mut EditContext) {
+        self.set_dynamic(context, !self.made_dynamic);
+    }
+
+    fn redo(&mut self, context: &mut EditContext) {
+        self.set_dynamic(context, self.made_dynamic);
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        if self.entity == from {
+            self.entity = to;
+        }
+    }
+}
+
+/// Several commands undone and redone as a single step, used for group edits
+#[derive(Default)]
+pub struct CommandGroup(pub Vec<Box<dyn EditCommand>>);
+
+impl CommandGroup {
+    pub fn push(&mut self, command: impl EditCommand) {
+        self.0.push(Box::new(command));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Later commands in the group may refer to a planet an earlier one respawned
+    fn remap_since(&mut self, context: &EditContext, start: usize) {
+        for &(from, to) in &context.respawned[start..] {
+            self.remap_entity(from, to);
+        }
+    }
+}
+
+impl EditCommand for CommandGroup {
+    fn undo(&mut self, context: &mut EditContext) {
+        for i in (0..self.0.len()).rev() {
+            let start = context.respawned.len();
+            self.0[i].undo(context);
+            self.remap_since(context, start);
+        }
+    }
+
+    fn redo(&mut self, context: &mut EditContext) {
+        for i in 0..self.0.len() {
+            let start = context.respawned.len();
+            self.0[i].redo(context);
+            self.remap_since(context, start);
+        }
+    }
+
+    fn remap_entity(&mut self, from: Entity, to: Entity) {
+        for command in &mut self.0 {
+            command.remap_entity(from, to);
+        }
+    }
+
+    fn can_merge(&self, next: &dyn EditCommand) -> bool {
+        (next as &dyn Any)
+            .downcast_ref::<Self>()
+            .is_some_and(|next| {
+                next.0.len() == self.0.len()
+                    && self
+                        .0
+                        .iter()
+                        .zip(&next.0)
+                        .all(|(command, next)| command.can_merge(next.as_ref()))
+            })
+    }
+
+    /// Only merges if every command of the group can, a partial merge can't be undone
+    fn merge(&mut self, next: &dyn EditCommand) -> bool {
+        match (next as &dyn Any).downcast_ref::<Self>() {
+            Some(next) if self.can_merge(next) => {
+                for (command, next) in self.0.iter_mut().zip(&next.0) {
+                    command.merge(next.as_ref());
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo
+pub(super) fn undo_redo_keybindings(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_context: EguiContexts,
+    mut history: ResMut<EditHistory>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    //Text fields have their own undo
+    if egui_context
+        .ctx_mut()
+        .is_ok_and(|ctx| ctx.wants_keyboard_input())
+    {
+        return;
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let mut context = EditContext {
+        commands: &mut commands,
+        meshes: &mut meshes,
+        materials: &mut materials,
+        respawned: Vec::new(),
+    };
+
+    if keys.just_pressed(KeyCode::KeyZ) && !shift {
+        history.undo(&mut context);
+    } else if keys.just_pressed(KeyCode::KeyY) || (keys.just_pressed(KeyCode::KeyZ) && shift) {
+        history.redo(&mut context);
+    }
+}
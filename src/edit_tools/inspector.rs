@@ -0,0 +1,395 @@
+//! Components shown in the planet editor
+//!
+//! Any [`EditableComponent`] can be added to the planet editor with
+//! [`RegisterEditable::register_editable`], it then shows up in a collapsible
+//! section of every editor window of an entity that has it.
+//!
+//! Every other component of the entity is listed below those sections, the
+//! ones implementing [`Reflect`] get an editor generated from their fields.
+
+use std::any::TypeId;
+
+use bevy::{
+    ecs::component::{Components, Mutable},
+    prelude::*,
+    reflect::{
+        DynamicEnum, DynamicVariant, Enum, PartialReflect, ReflectMut, TypeInfo, TypeRegistry,
+        VariantInfo,
+    },
+};
+use bevy_egui::egui::{CollapsingHeader, ComboBox, DragValue, Grid, Ui};
+
+use super::{
+    EditableComponent,
+    history::{EditHistory, ReflectChange},
+    window::{record_change, vec2_editor},
+};
+
+struct EditableEntry {
+    type_id: TypeId,
+    section: &'static str,
+    order: i32,
+    has: fn(&EntityMut) -> bool,
+    edit: fn(&mut Ui, &mut EntityMut, &mut EditHistory),
+}
+
+/// The components registered with [`RegisterEditable`], in the order they are shown
+#[derive(Resource, Default)]
+pub struct EditableRegistry {
+    entries: Vec<EditableEntry>,
+}
+
+impl EditableRegistry {
+    fn add<C>(&mut self)
+    where
+        C: EditableComponent<Mutability = Mutable>,
+    {
+        if self.is_registered(TypeId::of::<C>()) {
+            return;
+        }
+
+        let entry = EditableEntry {
+            type_id: TypeId::of::<C>(),
+            section: C::SECTION,
+            order: C::ORDER,
+            has: |entity| entity.contains::<C>(),
+            edit: try_edit_type::<C>,
+        };
+
+        //Registration order breaks ties
+        let index = self.entries.partition_point(|e| e.order <= entry.order);
+        self.entries.insert(index, entry);
+    }
+
+    pub fn is_registered(&self, type_id: TypeId) -> bool {
+        self.entries.iter().any(|e| e.type_id == type_id)
+    }
+
+    /// Sections in order of their first component
+    fn sections(&self) -> Vec<&'static str> {
+        let mut sections: Vec<&'static str> = Vec::new();
+
+        for entry in &self.entries {
+            if !sections.contains(&entry.section) {
+                sections.push(entry.section);
+            }
+        }
+
+        sections
+    }
+
+    /// Draws a section for every registered component the entity has
+    pub(super) fn draw_sections(
+        &self,
+        ui: &mut Ui,
+        entity: &mut EntityMut,
+        history: &mut EditHistory,
+    ) {
+        for section in self.sections() {
+            let entries: Vec<&EditableEntry> = self
+                .entries
+                .iter()
+                .filter(|e| e.section == section && (e.has)(entity))
+                .collect();
+
+            if entries.is_empty() {
+                continue;
+            }
+
+            CollapsingHeader::new(section)
+                .default_open(true)
+                .show(ui, |ui| {
+                    Grid::new(section).show(ui, |ui| {
+                        for entry in entries {
+                            (entry.edit)(ui, entity, history);
+                        }
+                    });
+                });
+        }
+    }
+}
+
+pub trait RegisterEditable {
+    /// Shows the component in the planet editor of every entity that has it
+    fn register_editable<C>(&mut self) -> &mut Self
+    where
+        C: EditableComponent<Mutability = Mutable>;
+}
+
+impl RegisterEditable for App {
+    fn register_editable<C>(&mut self) -> &mut Self
+    where
+        C: EditableComponent<Mutability = Mutable>,
+    {
+        self.init_resource::<EditableRegistry>();
+        self.world_mut()
+            .resource_mut::<EditableRegistry>()
+            .add::<C>();
+        self
+    }
+}
+
+fn try_edit_type<EditedType>(ui: &mut Ui, entity: &mut EntityMut, history: &mut EditHistory)
+where
+    EditedType: EditableComponent<Mutability = Mutable>,
+{
+    let id = entity.id();
+    let continuous = ui.ctx().dragged_id().is_some();
+
+    if let Some(mut to_be_edited) = entity.get_mut::<EditedType>() {
+        record_change(history, id, to_be_edited.as_mut(), continuous, |c| {
+            c.edit_ui(ui)
+        });
+    }
+}
+
+/// Lists every component the registered sections don't cover, reflected ones can be edited
+pub(super) fn draw_other_components(
+    ui: &mut Ui,
+    entity: &mut EntityMut,
+    components: &Components,
+    type_registry: &TypeRegistry,
+    registry: &EditableRegistry,
+    history: &mut EditHistory,
+) {
+    let infos: Vec<_> = entity
+        .archetype()
+        .components()
+        .iter()
+        .filter_map(|id| components.get_info(*id))
+        .filter(|info| !info.type_id().is_some_and(|t| registry.is_registered(t)))
+        .collect();
+
+    CollapsingHeader::new("Other Components")
+        .default_open(false)
+        .show(ui, |ui| {
+            for info in infos {
+                let name = info.name().shortname().to_string();
+                let reflect_component = info
+                    .type_id()
+                    .and_then(|t| type_registry.get_type_data::<ReflectComponent>(t))
+                    .filter(|_| info.mutable());
+
+                match reflect_component {
+                    Some(reflect_component) => {
+                        ui.collapsing(name, |ui| {
+                            edit_reflected(ui, entity, reflect_component, history)
+                        });
+                    }
+                    None => {
+                        ui.weak(name);
+                    }
+                }
+            }
+        });
+}
+
+/// Edits a copy of the component so it is only written, and recorded, when something changed
+fn edit_reflected(
+    ui: &mut Ui,
+    entity: &mut EntityMut,
+    reflect_component: &ReflectComponent,
+    history: &mut EditHistory,
+) {
+    let Some(current) = reflect_component.reflect(&*entity) else {
+        return;
+    };
+
+    let before = clone_reflected(current.as_partial_reflect());
+    let mut edited = clone_reflected(current.as_partial_reflect());
+
+    if !reflect_editor(ui, edited.as_mut()) {
+        return;
+    }
+
+    if let Some(mut component) = reflect_component.reflect_mut(&mut *entity) {
+        component.apply(edited.as_ref());
+    }
+
+    history.record(
+        ReflectChange {
+            entity: entity.id(),
+            component: reflect_component.clone(),
+            before,
+            after: edited,
+        },
+        ui.ctx().dragged_id().is_some(),
+    );
+}
+
+/// A copy of the concrete type if it can be cloned, a dynamic one otherwise
+pub(super) fn clone_reflected(value: &dyn PartialReflect) -> Box<dyn PartialReflect> {
+    value
+        .reflect_clone()
+        .map(|value| value.into_partial_reflect())
+        .unwrap_or_else(|_| value.to_dynamic())
+}
+
+/// Returns true if the value was changed
+fn reflect_editor(ui: &mut Ui, value: &mut dyn PartialReflect) -> bool {
+    if let Some(editor) = known_type_editor(ui, value) {
+        return editor;
+    }
+
+    let mut changed = false;
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(value) => {
+            for i in 0..value.field_len() {
+                let name = value.name_at(i).unwrap_or_default().to_string();
+
+                if let Some(field) = value.field_at_mut(i) {
+                    changed |= field_editor(ui, &name, field);
+                }
+            }
+        }
+        ReflectMut::TupleStruct(value) => {
+            for i in 0..value.field_len() {
+                if let Some(field) = value.field_mut(i) {
+                    changed |= field_editor(ui, &i.to_string(), field);
+                }
+            }
+        }
+        ReflectMut::Tuple(value) => {
+            for i in 0..value.field_len() {
+                if let Some(field) = value.field_mut(i) {
+                    changed |= field_editor(ui, &i.to_string(), field);
+                }
+            }
+        }
+        ReflectMut::List(value) => {
+            for i in 0..value.len() {
+                if let Some(element) = value.get_mut(i) {
+                    changed |= field_editor(ui, &i.to_string(), element);
+                }
+            }
+        }
+        ReflectMut::Array(value) => {
+            for i in 0..value.len() {
+                if let Some(element) = value.get_mut(i) {
+                    changed |= field_editor(ui, &i.to_string(), element);
+                }
+            }
+        }
+        ReflectMut::Enum(value) => {
+            changed |= variant_editor(ui, value);
+
+            for i in 0..value.field_len() {
+                let name = value
+                    .name_at(i)
+                    .map_or_else(|| i.to_string(), ToString::to_string);
+
+                if let Some(field) = value.field_at_mut(i) {
+                    changed |= field_editor(ui, &name, field);
+                }
+            }
+        }
+        _ => {
+            ui.weak(value.reflect_short_type_path());
+        }
+    }
+
+    changed
+}
+
+/// Simple values go on one line, nested ones are indented below their name
+fn field_editor(ui: &mut Ui, name: &str, value: &mut dyn PartialReflect) -> bool {
+    let nested = matches!(
+        value.reflect_mut(),
+        ReflectMut::Struct(_)
+            | ReflectMut::TupleStruct(_)
+            | ReflectMut::Tuple(_)
+            | ReflectMut::List(_)
+            | ReflectMut::Array(_)
+    ) && !is_known_type(value);
+
+    if nested {
+        ui.label(name);
+        ui.indent(name, |ui| reflect_editor(ui, value)).inner
+    } else {
+        //Sibling rows share an id otherwise, which the combo boxes of enums are stored under
+        ui.push_id(name, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                reflect_editor(ui, value)
+            })
+            .inner
+        })
+        .inner
+    }
+}
+
+/// Switches between the unit variants of an enum, variants with fields can't be made from nothing
+///
+/// The combo box is stored under the id of the ui, which [`field_editor`] salts with the field name
+fn variant_editor(ui: &mut Ui, value: &mut dyn Enum) -> bool {
+    let Some(TypeInfo::Enum(info)) = value.get_represented_type_info() else {
+        ui.label(value.variant_name().to_string());
+        return false;
+    };
+
+    let current = value.variant_name().to_string();
+    let mut selected = None;
+
+    ComboBox::from_id_salt(info.type_path())
+        .selected_text(current.clone())
+        .show_ui(ui, |ui| {
+            for variant in info.iter() {
+                if let VariantInfo::Unit(variant) = variant
+                    && ui
+                        .selectable_label(variant.name() == current, variant.name())
+                        .clicked()
+                {
+                    selected = Some(variant.name());
+                }
+            }
+        });
+
+    match selected {
+        Some(name) if name != current => value
+            .try_apply(&DynamicEnum::new(name, DynamicVariant::Unit))
+            .is_ok(),
+        _ => false,
+    }
+}
+
+fn is_known_type(value: &dyn PartialReflect) -> bool {
+    value.try_downcast_ref::<Vec2>().is_some() || value.try_downcast_ref::<Color>().is_some()
+}
+
+/// Editors for values that don't read well field by field, none if the type isn't one of them
+fn known_type_editor(ui: &mut Ui, value: &mut dyn PartialReflect) -> Option<bool> {
+    macro_rules! number_editor {
+        ($($number:ty),+) => {
+            $(
+                if let Some(number) = value.try_downcast_mut::<$number>() {
+                    return Some(ui.add(DragValue::new(number)).changed());
+                }
+            )+
+        };
+    }
+
+    number_editor!(f32, f64, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+    if let Some(value) = value.try_downcast_mut::<bool>() {
+        return Some(ui.checkbox(value, "").changed());
+    }
+    if let Some(value) = value.try_downcast_mut::<String>() {
+        return Some(ui.text_edit_singleline(value).changed());
+    }
+    if let Some(value) = value.try_downcast_mut::<Vec2>() {
+        return Some(vec2_editor(ui, value));
+    }
+    if let Some(value) = value.try_downcast_mut::<Color>() {
+        let mut color = value.to_srgba().to_f32_array();
+        let changed = ui.color_edit_button_rgba_unmultiplied(&mut color).changed();
+
+        if changed {
+            *value = Color::srgba(color[0], color[1], color[2], color[3]);
+        }
+
+        return Some(changed);
+    }
+
+    None
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+
+use super::clipboard::{Clipboard, clipboard_menu};
+use crate::{
+    level_builder::{LevelBuilder, LevelReplacer, PlanetBuilder},
+    physics::{Collider, PhysicsMaterial, PhysicsSettings, PhysicsTransform, PhysicsVelocity},
+};
+
+/// Where the editor saves and opens levels, editable in the level menu
+#[derive(Resource)]
+pub struct LevelFilePath(pub String);
+
+impl Default for LevelFilePath {
+    fn default() -> Self {
+        Self("assets/levels/editor.level.ron".to_string())
+    }
+}
+
+/// Writes every planet in the world to the [`LevelFilePath`]
+#[derive(Message)]
+pub struct SaveLevel;
+
+/// Despawns every planet and builds the level at the [`LevelFilePath`]
+#[derive(Message)]
+pub struct OpenLevel;
+
+pub(super) fn draw_level_menu(
+    mut context: EguiContexts,
+    mut path: ResMut<LevelFilePath>,
+    mut save_level_ew: MessageWriter<SaveLevel>,
+    mut open_level_ew: MessageWriter<OpenLevel>,
+    mut clipboard: ResMut<Clipboard>,
+) {
+    egui::TopBottomPanel::top("level_menu").show(
+        context.ctx_mut().expect("Egui context should exist"),
+        |ui| {
+            egui::MenuBar::new().ui(ui, |ui| {
+                ui.menu_button("Level", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("File");
+                        ui.text_edit_singleline(&mut path.0);
+                    });
+
+                    if ui.button("Save Level (Ctrl+S)").clicked() {
+                        save_level_ew.write(SaveLevel);
+                        ui.close();
+                    }
+                    if ui.button("Open Level (Ctrl+O)").clicked() {
+                        open_level_ew.write(OpenLevel);
+                        ui.close();
+                    }
+                });
+                ui.menu_button("Clipboard", |ui| clipboard_menu(ui, &mut clipboard));
+            });
+        },
+    );
+}
+
+pub(super) fn level_file_keybindings(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut egui_context: EguiContexts,
+    mut save_level_ew: MessageWriter<SaveLevel>,
+    mut open_level_ew: MessageWriter<OpenLevel>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    //Text fields take the keyboard while they are focused
+    if egui_context
+        .ctx_mut()
+        .is_ok_and(|ctx| ctx.wants_keyboard_input())
+    {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyS) {
+        save_level_ew.write(SaveLevel);
+    }
+    if keys.just_pressed(KeyCode::KeyO) {
+        open_level_ew.write(OpenLevel);
+    }
+}
+
+/// Everything a planet is saved from
+type SavedPlanetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static PhysicsMaterial,
+        &'static PhysicsTransform,
+        Option<&'static PhysicsVelocity>,
+        Option<&'static Collider>,
+        Option<&'static MeshMaterial2d<ColorMaterial>>,
+    ),
+>;
+
+pub(super) fn save_level(
+    mut save_level_er: MessageReader<SaveLevel>,
+    path: Res<LevelFilePath>,
+    physics_settings: Option<Res<PhysicsSettings>>,
+    planet_query: SavedPlanetQuery,
+    materials: Res<Assets<ColorMaterial>>,
+) {
+    if save_level_er.read().last().is_none() {
+        return;
+    }
+
+    let mut planets: Vec<_> = planet_query.iter().collect();
+    planets.sort_by_key(|(entity, ..)| entity.index());
+
+    let mut level = LevelBuilder::default();
+
+    if let Some(physics_settings) = physics_settings {
+        level = level.with_gravitational_constant(physics_settings.gravitational_constant);
+    }
+
+    for (_, material, transform, velocity, collider, color) in planets {
+        let color = color
+            .and_then(|c| materials.get(c))
+            .map_or(Color::WHITE, |c| c.color);
+
+        level = level.add_planet(PlanetBuilder::from_components(
+            material, transform, velocity, collider, color,
+        ));
+    }
+
+    match level.save(&path.0) {
+        Ok(()) => info!("Saved level to {}", path.0),
+        Err(e) => error!("Could not save level to {}: {}", path.0, e),
+    }
+}
+
+pub(super) fn open_level(
+    mut open_level_er: MessageReader<OpenLevel>,
+    path: Res<LevelFilePath>,
+    mut level_replacer: LevelReplacer,
+) {
+    if open_level_er.read().last().is_none() {
+        return;
+    }
+
+    //Keep the current scene if the file is bad
+    let level = match LevelBuilder::load(&path.0) {
+        Ok(level) => level,
+        Err(e) => {
+            error!("Could not open level {}: {}", path.0, e);
+            return;
+        }
+    };
+
+    level_replacer.replace(level);
+
+    info!("Opened level {}", path.0);
+}
@@ -1,7 +1,17 @@
+pub mod clipboard;
+mod diagnostics_window;
+mod handles;
+pub mod history;
+pub mod inspector;
+pub mod level_menu;
+mod orbit_readout;
+pub mod selection;
+pub mod trajectory;
 pub mod window;
 use std::marker::PhantomData;
 
 use bevy::{
+    ecs::component::Mutable,
     math::bounding::{BoundingCircle, IntersectsVolume},
     picking::{
         backend::{HitData, PointerHits},
@@ -10,12 +20,24 @@ use bevy::{
     prelude::*,
 };
 use bevy_egui::{EguiPrimaryContextPass, egui::Ui};
+use clipboard::{Clipboard, clipboard_keybindings};
+use diagnostics_window::draw_diagnostics_window;
+use handles::{HandleDrag, drag_handles, draw_velocity_handles};
+use history::{EditHistory, undo_redo_keybindings};
+use inspector::{EditableRegistry, RegisterEditable};
+use level_menu::{
+    LevelFilePath, OpenLevel, SaveLevel, draw_level_menu, level_file_keybindings, open_level,
+    save_level,
+};
+use orbit_readout::draw_osculating_orbits;
+use selection::{Selection, box_select, draw_selection, draw_selection_window};
+use trajectory::{TrajectoryPreview, draw_trajectory_previews};
 use window::{
-    CreateNewPlanet, create_planet_window, detect_planet_creation, draw_edit_windows,
-    toggle_editor_window,
+    CreateNewPlanet, PlanetCreator, create_planet_window, detect_planet_creation,
+    draw_edit_windows, toggle_editor_window,
 };
 
-use crate::physics::Collider;
+use crate::physics::{Collider, PhysicsTransform, PhysicsVelocity};
 
 /// This Plugin contains a picking backend for the physics objects and
 /// custom egui editor for editing physics objects
@@ -23,12 +45,48 @@ use crate::physics::Collider;
 /// ##Usage
 /// T should be the main camera as it is used to compute the position of
 /// mouse clicks within the game world
-#[derive(Default)]
+///
+/// Components of other modules are added to the planet editor with
+/// [`EditingToolsPlugin::register_editable`] or [`RegisterEditable`]
 pub struct EditingToolsPlugin<T>
 where
     T: Component,
 {
     pub main_camera_type: PhantomData<T>,
+    editables: Vec<fn(&mut App)>,
+}
+
+//Derived Default would require the camera marker to implement Default
+impl<T> Default for EditingToolsPlugin<T>
+where
+    T: Component,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EditingToolsPlugin<T>
+where
+    T: Component,
+{
+    pub fn new() -> Self {
+        Self {
+            main_camera_type: PhantomData,
+            editables: Vec::new(),
+        }
+    }
+
+    /// Shows the component in the planet editor of every entity that has it
+    pub fn register_editable<C>(mut self) -> Self
+    where
+        C: EditableComponent<Mutability = Mutable>,
+    {
+        self.editables.push(|app| {
+            app.register_editable::<C>();
+        });
+        self
+    }
 }
 
 impl<T> Plugin for EditingToolsPlugin<T>
@@ -37,13 +95,49 @@ where
 {
     fn build(&self, app: &mut App) {
         app.add_message::<CreateNewPlanet>();
+        app.add_message::<SaveLevel>();
+        app.add_message::<OpenLevel>();
+        app.init_resource::<LevelFilePath>();
+        app.init_resource::<EditHistory>();
+        app.init_resource::<PlanetCreator>();
+        app.init_resource::<HandleDrag>();
+        app.init_resource::<TrajectoryPreview>();
+        app.init_resource::<Selection>();
+        app.init_resource::<Clipboard>();
+        app.init_resource::<EditableRegistry>();
+        app.register_editable::<PhysicsTransform>()
+            .register_editable::<PhysicsVelocity>();
+        for register in &self.editables {
+            register(app);
+        }
         app.add_systems(
             Update,
-            (picking_backend_physics::<T>, detect_planet_creation::<T>),
+            (
+                picking_backend_physics::<T>,
+                detect_planet_creation::<T>,
+                (
+                    drag_handles::<T>,
+                    box_select::<T>,
+                    draw_velocity_handles::<T>,
+                    draw_selection,
+                    draw_trajectory_previews,
+                    draw_osculating_orbits,
+                )
+                    .chain(),
+                (level_file_keybindings, save_level, open_level).chain(),
+                undo_redo_keybindings,
+                clipboard_keybindings::<T>,
+            ),
         );
         app.add_systems(
             EguiPrimaryContextPass,
-            (draw_edit_windows, create_planet_window),
+            (
+                draw_level_menu,
+                draw_edit_windows,
+                draw_selection_window,
+                create_planet_window,
+                draw_diagnostics_window,
+            ),
         );
         app.add_observer(toggle_editor_window);
     }
@@ -94,6 +188,16 @@ pub fn picking_backend_physics<MainCameraMarker>(
     }
 }
 
-pub trait EditableComponent: Component {
-    fn edit_ui(&mut self, ui: &mut Ui);
+/// Components that can be edited in the planet editor, cloned so edits can be undone
+///
+/// The rows drawn by [`EditableComponent::edit_ui`] are laid out in a grid, so each
+/// should end with [`Ui::end_row`]
+pub trait EditableComponent: Component + Clone {
+    /// The collapsible section of the planet editor the component is shown in
+    const SECTION: &'static str = "Components";
+    /// Components with a lower order are shown first, sections are ordered by their first component
+    const ORDER: i32 = 0;
+
+    /// Returns true if the component was changed
+    fn edit_ui(&mut self, ui: &mut Ui) -> bool;
 }
@@ -0,0 +1,199 @@
+//! Read-only orbital elements for the planet editor windows
+//!
+//! The orbit of a planet is measured against the body pulling hardest on it and
+//! is the two body orbit it would follow if everything else vanished right now.
+
+use bevy::{color::palettes::css::AQUA, prelude::*};
+use bevy_egui::egui::{Grid, Ui};
+
+use super::window::EditorWindow;
+use crate::{
+    level_builder::PlanetBuilder,
+    physics::{
+        Collider, DEFAULT_GRAVITATIONAL_CONSTANT, PhysicsMaterial, PhysicsSettings,
+        PhysicsTransform, PhysicsVelocity,
+        orbit::{OrbitDirection, OrbitalElements, dominant_attractor},
+    },
+};
+
+const CONIC_SEGMENTS: usize = 128;
+/// Unbound orbits are drawn out to this many times the current distance to the parent
+const HYPERBOLA_EXTENT: f32 = 3.0;
+
+pub(super) struct OsculatingOrbit {
+    parent: Entity,
+    parent_position: Vec2,
+    distance: f32,
+    gravitational_parameter: f32,
+    elements: OrbitalElements,
+}
+
+impl OsculatingOrbit {
+    /// The orbit of `entity` around the dominant attractor among `bodies`
+    pub(super) fn find(
+        entity: Entity,
+        bodies: &[(Entity, PlanetBuilder)],
+        gravitational_constant: f32,
+    ) -> Option<Self> {
+        let (_, planet) = bodies.iter().find(|(e, _)| *e == entity)?;
+
+        let parent = dominant_attractor(
+            planet.position,
+            bodies
+                .iter()
+                .filter(|(e, _)| *e != entity)
+                .map(|(e, p)| (*e, p.mass, p.position)),
+        )?;
+        let (_, parent_planet) = bodies.iter().find(|(e, _)| *e == parent)?;
+
+        let position = planet.position - parent_planet.position;
+        let velocity =
+            planet.velocity.unwrap_or_default() - parent_planet.velocity.unwrap_or_default();
+        let gravitational_parameter = match parent_planet.velocity {
+            Some(_) => gravitational_constant * (planet.mass + parent_planet.mass),
+            None => gravitational_constant * parent_planet.mass,
+        };
+
+        Some(Self {
+            parent,
+            parent_position: parent_planet.position,
+            distance: position.length(),
+            gravitational_parameter,
+            elements: OrbitalElements::from_relative_state(
+                position,
+                velocity,
+                gravitational_parameter,
+            )?,
+        })
+    }
+
+    fn conic(&self) -> impl Iterator<Item = Vec2> {
+        self.elements
+            .conic_points(CONIC_SEGMENTS, self.distance * HYPERBOLA_EXTENT)
+            .into_iter()
+            .map(|point| point + self.parent_position)
+    }
+}
+
+/// Every body with physics, in the form orbits are computed from
+pub(super) fn orbit_bodies<'a>(
+    bodies: impl IntoIterator<
+        Item = (
+            Entity,
+            &'a PhysicsMaterial,
+            &'a PhysicsTransform,
+            Option<&'a PhysicsVelocity>,
+            Option<&'a Collider>,
+        ),
+    >,
+) -> Vec<(Entity, PlanetBuilder)> {
+    bodies
+        .into_iter()
+        .map(|(entity, material, transform, velocity, collider)| {
+            (
+                entity,
+                PlanetBuilder::from_components(
+                    material,
+                    transform,
+                    velocity,
+                    collider,
+                    Color::WHITE,
+                ),
+            )
+        })
+        .collect()
+}
+
+pub(super) fn gravitational_constant(settings: Option<&PhysicsSettings>) -> f32 {
+    settings.map_or(DEFAULT_GRAVITATIONAL_CONSTANT, |s| s.gravitational_constant)
+}
+
+pub(super) fn orbit_readout(ui: &mut Ui, orbit: Option<&OsculatingOrbit>, show_orbit: &mut bool) {
+    let Some(orbit) = orbit else {
+        ui.label("Nothing to orbit");
+        return;
+    };
+
+    let elements = &orbit.elements;
+    let distance = |value: Option<f32>| match value {
+        Some(value) if value.is_finite() => format!("{value:.1}"),
+        _ => "-".to_string(),
+    };
+
+    Grid::new("orbit readout").show(ui, |ui| {
+        ui.label("Parent: ");
+        ui.label(format!("Planet {}", orbit.parent.index()));
+        ui.end_row();
+
+        ui.label("Semi-major axis: ");
+        ui.label(distance(Some(elements.semi_major_axis)));
+        ui.end_row();
+
+        ui.label("Eccentricity: ");
+        ui.label(format!("{:.3}", elements.eccentricity));
+        ui.end_row();
+
+        ui.label("Periapsis: ");
+        ui.label(distance(Some(elements.periapsis())));
+        ui.end_row();
+
+        ui.label("Apoapsis: ");
+        ui.label(distance(elements.apoapsis()));
+        ui.end_row();
+
+        ui.label("Period: ");
+        ui.label(match elements.period(orbit.gravitational_parameter) {
+            Some(period) if period.is_finite() => format!("{period:.1} s"),
+            _ => "-".to_string(),
+        });
+        ui.end_row();
+
+        ui.label("Direction: ");
+        ui.label(match elements.direction {
+            OrbitDirection::Counterclockwise => "Counterclockwise",
+            OrbitDirection::Clockwise => "Clockwise",
+        });
+        ui.end_row();
+
+        ui.label(if elements.is_bound() {
+            "Bound"
+        } else {
+            "Unbound"
+        });
+        ui.end_row();
+    });
+
+    ui.checkbox(show_orbit, "Draw orbit");
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn draw_osculating_orbits(
+    mut gizmos: Gizmos,
+    settings: Option<Res<PhysicsSettings>>,
+    planet_query: Query<(
+        Entity,
+        &PhysicsMaterial,
+        &PhysicsTransform,
+        Option<&PhysicsVelocity>,
+        Option<&Collider>,
+    )>,
+    window_query: Query<(Entity, &EditorWindow), With<PhysicsVelocity>>,
+) {
+    let mut shown = window_query
+        .iter()
+        .filter(|(_, window)| window.get() && window.shows_orbit())
+        .peekable();
+
+    if shown.peek().is_none() {
+        return;
+    }
+
+    let bodies = orbit_bodies(planet_query.iter());
+    let gravitational_constant = gravitational_constant(settings.as_deref());
+
+    for (entity, _) in shown {
+        if let Some(orbit) = OsculatingOrbit::find(entity, &bodies, gravitational_constant) {
+            gizmos.linestrip_2d(orbit.conic(), AQUA.with_alpha(0.6));
+        }
+    }
+}
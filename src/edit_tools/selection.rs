@@ -0,0 +1,518 @@
+//! Editing several planets at once
+//!
+//! Shift clicking a planet adds it to or removes it from the selection, Shift
+//! dragging over empty space selects every planet inside the box. The Selection
+//! window edits the selected planets together, each operation is a single undo step.
+
+use bevy::{color::palettes::css::LIME, ecs::query::QueryData, prelude::*};
+use bevy_egui::{
+    EguiContexts,
+    egui::{self, DragValue, Grid},
+};
+
+use super::{
+    handles::{HandleDrag, camera_scale},
+    history::{ColorChange, CommandGroup, ComponentChange, EditHistory, PlanetExistence},
+    window::vec2_editor,
+};
+use crate::{
+    level_builder::{PlanetBuilder, calculate_radius, radius_follows_mass},
+    physics::{Collider, PhysicsMaterial, PhysicsTransform, PhysicsVelocity},
+};
+
+/// The selected planets with everything the Selection window edits
+type SelectedPlanetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static mut PhysicsMaterial,
+        &'static mut PhysicsTransform,
+        Option<&'static mut PhysicsVelocity>,
+        Option<&'static MeshMaterial2d<ColorMaterial>>,
+        Option<&'static mut Collider>,
+    ),
+>;
+
+/// Where duplicates end up relative to the originals
+pub(super) const DUPLICATE_OFFSET: Vec2 = Vec2::new(50.0, -50.0);
+/// In screen pixels, a smaller box counts as a plain click
+const MIN_BOX_SIZE: f32 = 4.0;
+
+#[derive(Resource, Default)]
+pub struct Selection {
+    entities: Vec<Entity>,
+    /// Corners of the box while it is being dragged out, in world space
+    box_corners: Option<(Vec2, Vec2)>,
+    translation: Vec2,
+    velocity_offset: Vec2,
+}
+
+impl Selection {
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn add(&mut self, entity: Entity) {
+        if !self.contains(entity) {
+            self.entities.push(entity);
+        }
+    }
+
+    pub fn toggle(&mut self, entity: Entity) {
+        if self.contains(entity) {
+            self.entities.retain(|e| *e != entity);
+        } else {
+            self.entities.push(entity);
+        }
+    }
+
+    /// Replaces the selection
+    pub fn set(&mut self, entities: impl IntoIterator<Item = Entity>) {
+        self.entities.clear();
+        self.entities.extend(entities);
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn box_select<T>(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera_query: Query<(&Camera, &GlobalTransform, &Projection), With<T>>,
+    window_query: Query<&Window>,
+    mut egui_context: EguiContexts,
+    handle_drag: Res<HandleDrag>,
+    planet_query: Query<(Entity, &PhysicsTransform)>,
+    mut selection: ResMut<Selection>,
+) where
+    T: Component,
+{
+    let Ok((camera, camera_transform, projection)) = camera_query.single() else {
+        return;
+    };
+    let cursor = window_query
+        .single()
+        .ok()
+        .and_then(|w| w.cursor_position())
+        .and_then(|c| camera.viewport_to_world_2d(camera_transform, c).ok());
+
+    if mouse_buttons.just_released(MouseButton::Left) {
+        let Some((start, end)) = selection.box_corners.take() else {
+            return;
+        };
+        let end = cursor.unwrap_or(end);
+
+        let area = Rect::from_corners(start, end);
+        if area.size().max_element() < MIN_BOX_SIZE * camera_scale(Some(projection)) {
+            return;
+        }
+
+        for (entity, transform) in planet_query.iter() {
+            if area.contains(transform.location()) {
+                selection.add(entity);
+            }
+        }
+
+        return;
+    }
+
+    let Some(cursor) = cursor else {
+        return;
+    };
+
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let over_egui = egui_context
+            .ctx_mut()
+            .is_ok_and(|ctx| ctx.is_pointer_over_area());
+
+        //Grabbing a planet or a handle takes priority
+        if shift && !over_egui && !handle_drag.has_target() {
+            selection.box_corners = Some((cursor, cursor));
+        }
+    } else if let Some((_, end)) = &mut selection.box_corners {
+        *end = cursor;
+    }
+}
+
+pub(super) fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    planet_query: Query<(&PhysicsTransform, &PhysicsMaterial, Option<&Collider>)>,
+) {
+    for entity in selection.entities() {
+        let Ok((transform, material, collider)) = planet_query.get(*entity) else {
+            continue;
+        };
+        let radius = collider.map_or(calculate_radius(material.mass), |c| c.0.circle.radius);
+
+        gizmos.circle_2d(transform.location(), radius * 1.2 + 2.0, LIME);
+    }
+
+    if let Some((start, end)) = selection.box_corners {
+        let area = Rect::from_corners(start, end);
+
+        gizmos.rect_2d(area.center(), area.size(), LIME.with_alpha(0.6));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn draw_selection_window(
+    mut context: EguiContexts,
+    mut selection: ResMut<Selection>,
+    mut planet_query: SelectedPlanetQuery,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut history: ResMut<EditHistory>,
+    mut commands: Commands,
+) {
+    //Planets may have been despawned by other tools
+    if selection
+        .entities
+        .iter()
+        .any(|e| !planet_query.contains(*e))
+    {
+        selection.entities.retain(|e| planet_query.contains(*e));
+    }
+    if selection.is_empty() {
+        return;
+    }
+
+    let Ok(ctx) = context.ctx_mut() else {
+        return;
+    };
+    let ctx = ctx.clone();
+    let selection = selection.as_mut();
+    let entities = selection.entities.clone();
+
+    let mut is_open = true;
+    let mut action = None;
+
+    egui::Window::new("Selection")
+        .resizable([false; 2])
+        .open(&mut is_open)
+        .show(&ctx, |ui| {
+            let dragging = ui.ctx().dragged_id().is_some();
+
+            Grid::new("selection").show(ui, |ui| {
+                ui.label("Selected: ");
+                ui.label(entities.len().to_string());
+                ui.end_row();
+
+                edit_mass(ui, &entities, &mut planet_query, &mut history, dragging);
+                edit_color(
+                    ui,
+                    &entities,
+                    &planet_query,
+                    &mut materials,
+                    &mut history,
+                    dragging,
+                );
+
+                ui.label("Velocity offset: ");
+                vec2_editor(ui, &mut selection.velocity_offset);
+                if ui.button("Add Velocity").clicked() {
+                    action = Some(GroupAction::AddVelocity);
+                }
+                ui.end_row();
+
+                ui.label("Translate: ");
+                vec2_editor(ui, &mut selection.translation);
+                if ui.button("Move").clicked() {
+                    action = Some(GroupAction::Translate);
+                }
+                ui.end_row();
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Duplicate").clicked() {
+                    action = Some(GroupAction::Duplicate);
+                }
+                if ui.button("Deselect").clicked() {
+                    action = Some(GroupAction::Deselect);
+                }
+                if ui.button("Delete").clicked() {
+                    action = Some(GroupAction::Delete);
+                }
+            });
+        });
+
+    if !is_open {
+        action = Some(GroupAction::Deselect);
+    }
+
+    let Some(action) = action else {
+        return;
+    };
+
+    match action {
+        GroupAction::AddVelocity => {
+            let offset = selection.velocity_offset;
+            let mut group = CommandGroup::default();
+
+            for entity in &entities {
+                if let Ok((_, _, Some(mut velocity), ..)) = planet_query.get_mut(*entity) {
+                    let before = *velocity;
+                    velocity.velocity += offset;
+                    group.push(ComponentChange {
+                        entity: *entity,
+                        before,
+                        after: *velocity,
+                    });
+                }
+            }
+
+            record_group(&mut history, group, false);
+        }
+        GroupAction::Translate => {
+            let offset = selection.translation;
+            let mut group = CommandGroup::default();
+
+            for entity in &entities {
+                if let Ok((_, mut transform, ..)) = planet_query.get_mut(*entity) {
+                    let before = *transform;
+                    transform.location += offset;
+                    group.push(ComponentChange {
+                        entity: *entity,
+                        before,
+                        after: *transform,
+                    });
+                }
+            }
+
+            record_group(&mut history, group, false);
+        }
+        GroupAction::Duplicate => {
+            let copies = snapshot(&entities, &planet_query, &materials)
+                .into_iter()
+                .map(|(_, planet)| PlanetBuilder {
+                    position: planet.position + DUPLICATE_OFFSET,
+                    ..planet
+                })
+                .collect();
+
+            let copies = spawn_planets(
+                copies,
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut history,
+            );
+            selection.set(copies);
+        }
+        GroupAction::Delete => {
+            let mut group = CommandGroup::default();
+
+            for (entity, planet) in snapshot(&entities, &planet_query, &materials) {
+                group.push(PlanetExistence::despawned(entity, planet));
+                commands.entity(entity).despawn();
+            }
+
+            record_group(&mut history, group, false);
+            selection.clear();
+        }
+        GroupAction::Deselect => selection.clear(),
+    }
+}
+
+enum GroupAction {
+    AddVelocity,
+    Translate,
+    Duplicate,
+    Delete,
+    Deselect,
+}
+
+fn edit_mass(
+    ui: &mut egui::Ui,
+    entities: &[Entity],
+    planet_query: &mut SelectedPlanetQuery,
+    history: &mut EditHistory,
+    dragging: bool,
+) {
+    let masses: Vec<f32> = entities
+        .iter()
+        .filter_map(|e| planet_query.get(*e).ok())
+        .map(|(material, ..)| material.mass)
+        .collect();
+    let Some(&first) = masses.first() else {
+        return;
+    };
+    let mixed = masses.iter().any(|m| *m != first);
+
+    let mut mass = first;
+    ui.label(if mixed { "Mass (mixed): " } else { "Mass: " });
+    let changed = ui
+        .add(DragValue::new(&mut mass).range(0.0..=f32::INFINITY))
+        .changed();
+    ui.end_row();
+
+    if !changed {
+        return;
+    }
+
+    let mut group = CommandGroup::default();
+
+    for entity in entities {
+        let Ok((mut material, _, _, _, collider)) = planet_query.get_mut(*entity) else {
+            continue;
+        };
+
+        //Planets with a radius of their own keep it
+        if let Some(mut collider) = collider
+            && radius_follows_mass(collider.0.circle.radius, material.mass)
+        {
+            let before = *collider;
+            collider.0.circle.radius = calculate_radius(mass);
+            group.push(ComponentChange {
+                entity: *entity,
+                before,
+                after: *collider,
+            });
+        }
+
+        let before = *material;
+        material.mass = mass;
+        group.push(ComponentChange {
+            entity: *entity,
+            before,
+            after: *material,
+        });
+    }
+
+    record_group(history, group, dragging);
+}
+
+fn edit_color(
+    ui: &mut egui::Ui,
+    entities: &[Entity],
+    planet_query: &SelectedPlanetQuery,
+    materials: &mut Assets<ColorMaterial>,
+    history: &mut EditHistory,
+    dragging: bool,
+) {
+    let handles: Vec<(Entity, Handle<ColorMaterial>)> = entities
+        .iter()
+        .filter_map(|e| {
+            let (.., color, _) = planet_query.get(*e).ok()?;
+            Some((*e, color?.0.clone()))
+        })
+        .collect();
+    let colors: Vec<Color> = handles
+        .iter()
+        .filter_map(|(_, handle)| materials.get(handle))
+        .map(|m| m.color)
+        .collect();
+    let Some(&first) = colors.first() else {
+        return;
+    };
+    let mixed = colors.iter().any(|c| *c != first);
+
+    let mut color = first.to_linear().to_f32_array();
+    ui.label(if mixed { "Color (mixed): " } else { "Color: " });
+    let changed = ui
+        .color_edit_button_rgba_premultiplied(&mut color)
+        .changed();
+    ui.end_row();
+
+    if !changed {
+        return;
+    }
+
+    let color = Color::LinearRgba(LinearRgba::from_f32_array(color));
+    let mut group = CommandGroup::default();
+
+    for (entity, handle) in handles {
+        let Some(material) = materials.get_mut(&handle) else {
+            continue;
+        };
+
+        group.push(ColorChange {
+            entity,
+            before: material.color,
+            after: color,
+        });
+        material.color = color;
+    }
+
+    record_group(history, group, dragging);
+}
+
+/// What [`snapshot`] reads of a planet, the query can also have mutable access to it
+pub(super) type SnapshotData = (
+    &'static PhysicsMaterial,
+    &'static PhysicsTransform,
+    Option<&'static PhysicsVelocity>,
+    Option<&'static MeshMaterial2d<ColorMaterial>>,
+    Option<&'static Collider>,
+);
+
+/// The selected planets as they are now, enough to respawn them
+pub(super) fn snapshot<D>(
+    entities: &[Entity],
+    planet_query: &Query<D>,
+    materials: &Assets<ColorMaterial>,
+) -> Vec<(Entity, PlanetBuilder)>
+where
+    D: QueryData<ReadOnly = SnapshotData>,
+{
+    entities
+        .iter()
+        .filter_map(|e| Some((*e, planet_query.get(*e).ok()?)))
+        .map(
+            |(entity, (material, transform, velocity, color, collider))| {
+                let color = color
+                    .and_then(|c| materials.get(&c.0))
+                    .map_or(Color::WHITE, |c| c.color);
+
+                (
+                    entity,
+                    PlanetBuilder::from_components(material, transform, velocity, collider, color),
+                )
+            },
+        )
+        .collect()
+}
+
+/// Spawns planets as a single undo step
+pub(super) fn spawn_planets(
+    planets: Vec<PlanetBuilder>,
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    history: &mut EditHistory,
+) -> Vec<Entity> {
+    let mut group = CommandGroup::default();
+    let mut entities = Vec::new();
+
+    for planet in planets {
+        let entity = planet
+            .clone()
+            .non_negative()
+            .build()
+            .build(commands, meshes, materials);
+
+        group.push(PlanetExistence::spawned(entity, planet));
+        entities.push(entity);
+    }
+
+    record_group(history, group, false);
+    entities
+}
+
+fn record_group(history: &mut EditHistory, group: CommandGroup, continuous: bool) {
+    if !group.is_empty() {
+        history.record(group, continuous);
+    }
+}
@@ -0,0 +1,153 @@
+use bevy::{color::palettes::css::RED, prelude::*};
+
+use super::window::{EditorWindow, PlanetCreator};
+use crate::{
+    level_builder::calculate_radius,
+    physics::{
+        Collider, Frozen, PhysicsMaterial, PhysicsSettings, PhysicsTransform, PhysicsVelocity,
+        collision::CollisionPolicy,
+        integrator::Body,
+        prediction::{PredictedBody, Trajectory, predict_trajectories},
+    },
+};
+
+/// Caps the cost of a preview when the fixed timestep is tiny
+const MAX_PREVIEW_STEPS: usize = 4096;
+
+/// Draws where the planet being created and planets with an open editor window
+/// will go, predicted again whenever the planets, the creator or the
+/// [`PhysicsSettings`] change so it follows edits live
+#[derive(Resource)]
+pub struct TrajectoryPreview {
+    pub enabled: bool,
+    /// How far ahead to predict, in simulated seconds
+    pub seconds: f32,
+}
+
+impl Default for TrajectoryPreview {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            seconds: 10.0,
+        }
+    }
+}
+
+/// The last prediction and what it was predicted from
+#[derive(Default)]
+pub(super) struct PreviewCache {
+    inputs: Option<PreviewInputs>,
+    trajectories: Vec<Trajectory>,
+}
+
+#[derive(PartialEq)]
+struct PreviewInputs {
+    settings: PhysicsSettings,
+    bodies: Vec<PredictedBody>,
+    colors: Vec<Color>,
+    delta: f32,
+    steps: usize,
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn draw_trajectory_previews(
+    mut gizmos: Gizmos,
+    mut cache: Local<PreviewCache>,
+    preview: Res<TrajectoryPreview>,
+    settings: Option<Res<PhysicsSettings>>,
+    fixed_time: Res<Time<Fixed>>,
+    creator: Res<PlanetCreator>,
+    planet_query: Query<
+        (
+            &PhysicsMaterial,
+            &PhysicsTransform,
+            Option<&PhysicsVelocity>,
+            Option<&Collider>,
+            Option<&CollisionPolicy>,
+            Option<&EditorWindow>,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    let Some(settings) = settings else {
+        return;
+    };
+    if !preview.enabled {
+        return;
+    }
+
+    let mut bodies: Vec<PredictedBody> = planet_query
+        .iter()
+        .map(
+            |(material, transform, velocity, collider, policy, window)| PredictedBody {
+                body: Body::from_components(material, transform, velocity),
+                radius: collider.map_or(calculate_radius(material.mass), |c| c.0.circle.radius),
+                policy: policy.map(|p| p.0),
+                tracked: velocity.is_some() && window.is_some_and(|w| w.get()),
+            },
+        )
+        .collect();
+
+    let mut colors: Vec<Color> = bodies
+        .iter()
+        .filter(|b| b.tracked)
+        .map(|_| Color::WHITE.with_alpha(0.5))
+        .collect();
+
+    if let Some(planet) = creator.planet.as_ref().filter(|_| creator.is_open)
+        && let Some(velocity) = planet.velocity
+    {
+        bodies.push(PredictedBody {
+            body: Body {
+                mass: planet.mass,
+                position: planet.position,
+                velocity,
+                acceleration: Vec2::ZERO,
+                is_dynamic: true,
+            },
+            radius: planet.radius.unwrap_or(calculate_radius(planet.mass)),
+            policy: None,
+            tracked: true,
+        });
+        colors.push(planet.color.with_alpha(0.6));
+    }
+
+    if colors.is_empty() {
+        return;
+    }
+
+    let delta = fixed_time.timestep().as_secs_f32();
+    let steps = ((preview.seconds / delta).ceil() as usize).min(MAX_PREVIEW_STEPS);
+
+    //The editor windows and the creator hand their values to egui mutably every frame,
+    //so change detection would always fire and the inputs are compared instead. They
+    //also differ every time the simulation advances
+    let inputs = PreviewInputs {
+        settings: settings.clone(),
+        bodies,
+        colors,
+        delta,
+        steps,
+    };
+    if cache.inputs.as_ref() != Some(&inputs) {
+        cache.trajectories = predict_trajectories(&settings, &inputs.bodies, delta, steps);
+        cache.inputs = Some(inputs);
+    }
+
+    let PreviewCache {
+        inputs: Some(inputs),
+        trajectories,
+    } = &*cache
+    else {
+        return;
+    };
+
+    for (trajectory, color) in trajectories.iter().zip(&inputs.colors) {
+        gizmos.linestrip_2d(trajectory.points.iter().copied(), *color);
+
+        if let Some(collision) = trajectory.collision {
+            gizmos.circle_2d(collision, 10.0, RED);
+            gizmos.cross_2d(collision, 10.0, RED);
+        }
+    }
+}
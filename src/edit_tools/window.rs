@@ -1,23 +1,53 @@
-use bevy::{ecs::component::Mutable, prelude::*};
+use bevy::{ecs::component::Components, prelude::*};
 use bevy_egui::{
     EguiContexts,
-    egui::{self, DragValue, Grid, Ui},
+    egui::{self, Button, DragValue, Grid, Ui},
 };
 use rand::random;
 
 use crate::{
-    edit_tools::EditableComponent,
-    level_builder::{PlanetBuilder, calculate_radius},
-    physics::{PhysicsMaterial, PhysicsTransform, PhysicsVelocity},
+    edit_tools::{
+        handles::HandleDrag,
+        history::{
+            ColorChange, CommandGroup, ComponentChange, DynamicChange, EditHistory, PlanetExistence,
+        },
+        inspector::{EditableRegistry, draw_other_components},
+        orbit_readout::{OsculatingOrbit, gravitational_constant, orbit_bodies, orbit_readout},
+        selection::Selection,
+    },
+    graphics::trails::Trail,
+    level_builder::{
+        PlanetBuilder, calculate_density, calculate_mass, calculate_radius, radius_follows_mass,
+    },
+    physics::{
+        Collider, DEFAULT_GRAVITATIONAL_CONSTANT, PhysicsMaterial, PhysicsSettings,
+        PhysicsTransform, PhysicsVelocity,
+        orbit::{OrbitDirection, OrbitalElements, dominant_attractor},
+    },
 };
 
 #[derive(Message)]
-pub(super) struct CreateNewPlanet(Vec2);
+pub(super) struct CreateNewPlanet {
+    position: Vec2,
+    /// Start on a circular orbit around the body pulling hardest at the position
+    circular_orbit: bool,
+}
+
+/// The planet being set up in the Planet Creator, not spawned yet
+#[derive(Resource, Default)]
+pub(super) struct PlanetCreator {
+    pub(super) planet: Option<PlanetBuilder>,
+    pub(super) is_open: bool,
+    orbit: OrbitalElements,
+    orbit_parent: Option<Entity>,
+}
 
-#[derive(Component)]
+#[derive(Component, Clone, PartialEq)]
 pub struct EditorWindow {
     is_open: bool,
     just_changed: bool,
+    /// Draw the osculating orbit while the window is open
+    show_orbit: bool,
 }
 
 impl Default for EditorWindow {
@@ -31,6 +61,7 @@ impl EditorWindow {
         Self {
             is_open,
             just_changed: true,
+            show_orbit: false,
         }
     }
 
@@ -38,6 +69,10 @@ impl EditorWindow {
         self.is_open
     }
 
+    pub fn shows_orbit(&self) -> bool {
+        self.show_orbit
+    }
+
     pub fn set(&mut self, is_open: bool) {
         self.is_open = is_open;
         self.just_changed = true;
@@ -49,9 +84,26 @@ impl EditorWindow {
     }
 }
 
-pub(super) fn toggle_editor_window(click: On<Pointer<Click>>, mut commands: Commands) {
+pub(super) fn toggle_editor_window(
+    click: On<Pointer<Click>>,
+    handle_drag: Res<HandleDrag>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<Selection>,
+    mut commands: Commands,
+) {
+    //Releasing a dragged planet also counts as a click
+    if handle_drag.is_dragging() {
+        return;
+    }
+
     let click = click.event_target();
 
+    //Shift clicking selects instead of opening a window
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        selection.toggle(click);
+        return;
+    }
+
     commands
         .entity(click)
         .entry::<EditorWindow>()
@@ -86,20 +138,30 @@ pub(super) fn detect_planet_creation<T>(
         }
     };
 
-    create_planet_ew.write(CreateNewPlanet(planet_pos));
+    create_planet_ew.write(CreateNewPlanet {
+        position: planet_pos,
+        circular_orbit: keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+    });
 }
 
 #[allow(clippy::too_many_arguments)]
 pub(super) fn create_planet_window(
     mut gizmos: Gizmos,
     mut create_planet_event: EventReader<CreateNewPlanet>,
-    mut current_planet: Local<Option<PlanetBuilder>>,
-    mut is_open: Local<bool>,
+    mut creator: ResMut<PlanetCreator>,
     mut context: EguiContexts,
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut history: ResMut<EditHistory>,
     window_query: Query<&Window>,
+    planet_query: Query<(
+        Entity,
+        &PhysicsMaterial,
+        &PhysicsTransform,
+        Option<&PhysicsVelocity>,
+    )>,
+    physics_settings: Option<Res<PhysicsSettings>>,
 ) {
     let Ok(cursor_pos) = window_query
         .single()
@@ -111,16 +173,44 @@ pub(super) fn create_planet_window(
 
     let mut window = egui::Window::new("Planet Creator");
 
+    let PlanetCreator {
+        planet,
+        is_open,
+        orbit,
+        orbit_parent,
+    } = &mut *creator;
+
+    let gravitational_constant =
+        physics_settings.map_or(DEFAULT_GRAVITATIONAL_CONSTANT, |s| s.gravitational_constant);
+    let parents: Vec<(Entity, PlanetBuilder)> = planet_query
+        .iter()
+        .map(|(entity, material, transform, velocity)| {
+            (
+                entity,
+                PlanetBuilder::from_components(material, transform, velocity, None, Color::WHITE),
+            )
+        })
+        .collect();
+
     if let Some(planet_creation_event) = create_planet_event.read().next() {
-        let planet_ref = current_planet.get_or_insert_default();
+        let planet_ref = planet.get_or_insert_default();
 
-        planet_ref.position = planet_creation_event.0;
+        planet_ref.position = planet_creation_event.position;
         planet_ref.color = Color::hsv(random::<f32>() * 360.0, 1.0, 1.0);
         *is_open = true;
         window = window.current_pos(cursor_pos.to_array());
+
+        if planet_creation_event.circular_orbit {
+            place_in_circular_orbit(
+                planet_ref,
+                &parents,
+                orbit.direction,
+                gravitational_constant,
+            );
+        }
     }
 
-    let Some(current_planet) = &mut *current_planet else {
+    let Some(current_planet) = planet else {
         return;
     };
 
@@ -138,7 +228,7 @@ pub(super) fn create_planet_window(
     window
         .resizable([false; 2])
         .collapsible(false)
-        .open(&mut is_open)
+        .open(is_open)
         .show(
             context.ctx_mut().expect("Egui context should exist"),
             |ui| {
@@ -164,20 +254,36 @@ pub(super) fn create_planet_window(
 
                     //Mass editor
                     ui.label("Mass");
-                    ui.add(DragValue::new(&mut current_planet.mass));
+                    ui.add(DragValue::new(&mut current_planet.mass).range(0.0..=f32::INFINITY));
                     ui.end_row();
 
                     //Create Button
                     if ui.button("Create Planet").clicked() {
-                        current_planet.clone().build().build(
+                        let entity = current_planet.clone().build().build(
                             &mut commands,
                             &mut meshes,
                             &mut materials,
                         );
 
+                        history.record(
+                            PlanetExistence::spawned(entity, current_planet.clone()),
+                            false,
+                        );
+
                         should_close = true;
                     }
                 });
+
+                ui.collapsing("Orbit", |ui| {
+                    orbit_editor(
+                        ui,
+                        current_planet,
+                        orbit,
+                        orbit_parent,
+                        &parents,
+                        gravitational_constant,
+                    );
+                });
             },
         );
 
@@ -186,131 +292,454 @@ pub(super) fn create_planet_window(
     }
 }
 
-macro_rules! edit_components {
-    ($ui:expr, $entity:expr, $($edited_type:ty),+) => {
-        $(
-            try_edit_type::<$edited_type>($ui, $entity);
-        )+
-    };
+/// Places the planet being created on an orbit around an existing planet
+fn orbit_editor(
+    ui: &mut Ui,
+    planet: &mut PlanetBuilder,
+    elements: &mut OrbitalElements,
+    parent: &mut Option<Entity>,
+    parents: &[(Entity, PlanetBuilder)],
+    gravitational_constant: f32,
+) {
+    let planet_name = |entity: Entity| format!("Planet {}", entity.index());
+
+    Grid::new("orbit").show(ui, |ui| {
+        ui.label("Parent");
+        egui::ComboBox::from_id_salt("orbit_parent")
+            .selected_text(parent.map_or("None".to_string(), planet_name))
+            .show_ui(ui, |ui| {
+                for (entity, _) in parents {
+                    ui.selectable_value(parent, Some(*entity), planet_name(*entity));
+                }
+            });
+        ui.end_row();
+
+        ui.label("Semi-major axis");
+        ui.add(DragValue::new(&mut elements.semi_major_axis).range(0.0..=f32::MAX));
+        ui.end_row();
+
+        ui.label("Eccentricity");
+        ui.add(
+            DragValue::new(&mut elements.eccentricity)
+                .range(0.0..=0.99)
+                .speed(0.01),
+        );
+        ui.end_row();
+
+        ui.label("Argument of periapsis");
+        ui.drag_angle(&mut elements.argument_of_periapsis);
+        ui.end_row();
+
+        ui.label("True anomaly");
+        ui.drag_angle(&mut elements.true_anomaly);
+        ui.end_row();
+
+        ui.label("Direction");
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut elements.direction,
+                OrbitDirection::Counterclockwise,
+                "Counterclockwise",
+            );
+            ui.radio_value(
+                &mut elements.direction,
+                OrbitDirection::Clockwise,
+                "Clockwise",
+            );
+        });
+        ui.end_row();
+    });
+
+    let selected_parent = parent
+        .and_then(|parent| parents.iter().find(|(entity, _)| *entity == parent))
+        .map(|(_, parent)| parent);
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(selected_parent.is_some(), Button::new("Place in Orbit"))
+            .clicked()
+            && let Some(parent) = selected_parent
+            && let Some(orbiting) =
+                planet
+                    .clone()
+                    .orbiting(parent, elements, gravitational_constant)
+        {
+            *planet = orbiting;
+        }
+
+        if ui.button("Circular Orbit Here").clicked() {
+            place_in_circular_orbit(planet, parents, elements.direction, gravitational_constant);
+        }
+    });
 }
 
+/// Keeps the position of the planet and makes it circle the body pulling hardest on it
+fn place_in_circular_orbit(
+    planet: &mut PlanetBuilder,
+    parents: &[(Entity, PlanetBuilder)],
+    direction: OrbitDirection,
+    gravitational_constant: f32,
+) {
+    let dominant = dominant_attractor(
+        planet.position,
+        parents
+            .iter()
+            .map(|(_, parent)| (parent, parent.mass, parent.position)),
+    );
+
+    if let Some(parent) = dominant {
+        *planet = planet
+            .clone()
+            .circular_orbit(parent, direction, gravitational_constant);
+    }
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub(super) fn draw_edit_windows(
-    mut context: EguiContexts,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut window_object_query: Query<(
-        Entity,
-        &mut EditorWindow,
-        Option<&mut MeshMaterial2d<ColorMaterial>>,
-        Option<&mut Mesh2d>,
-        Option<&mut PhysicsTransform>,
-        Option<&mut PhysicsVelocity>,
-        Option<&mut PhysicsMaterial>,
+    mut params: ParamSet<(
+        EguiContexts,
+        Query<&Window>,
+        Query<(
+            Entity,
+            &mut EditorWindow,
+            Option<&MeshMaterial2d<ColorMaterial>>,
+            Option<&PhysicsTransform>,
+            Option<&PhysicsVelocity>,
+            Option<&mut PhysicsMaterial>,
+            Option<&mut Collider>,
+        )>,
+        //Registered components can be anything on the entity
+        Query<EntityMut, With<EditorWindow>>,
     )>,
-    window_query: Query<&Window>,
+    registry: Res<EditableRegistry>,
+    components: &Components,
+    type_registry: Res<AppTypeRegistry>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut history: ResMut<EditHistory>,
+    physics_settings: Option<Res<PhysicsSettings>>,
     mut commands: Commands,
 ) {
-    let Ok(cursor_pos) = window_query.single().map(|w| w.cursor_position()) else {
+    let Ok(cursor_pos) = params.p1().single().map(|w| w.cursor_position()) else {
         warn!("Could not find a window to draw editor in");
         return;
     };
 
-    for (
-        entity,
-        mut open_window,
-        color_material,
-        mesh_handle,
-        physics_transform,
-        physics_velocity,
-        physics_material,
-    ) in window_object_query.iter_mut()
-    {
+    let ctx = params
+        .p0()
+        .ctx_mut()
+        .expect("Egui context should exist")
+        .clone();
+
+    //Every drag becomes its own undo step
+    if ctx.dragged_id().is_none() {
+        history.stop_coalescing();
+    }
+
+    let bodies = orbit_bodies(params.p2().iter().filter_map(
+        |(entity, _, _, transform, velocity, material, collider)| {
+            Some((entity, material?, transform?, velocity, collider))
+        },
+    ));
+    let gravitational_constant = gravitational_constant(physics_settings.as_deref());
+    let type_registry = type_registry.read();
+
+    let open_windows: Vec<(Entity, EditorWindow, bool)> = params
+        .p2()
+        .iter()
+        .filter(|(_, window, ..)| window.is_open)
+        .map(|(entity, window, _, _, velocity, ..)| (entity, window.clone(), velocity.is_some()))
+        .collect();
+
+    for (entity, mut open_window, is_dynamic) in open_windows {
         let mut window = egui::Window::new(format!("Planet Editor {}", entity.index()));
 
         if let Some(cursor_pos) = cursor_pos
             && open_window.just_changed
         {
             window = window.current_pos(cursor_pos.to_array());
-            open_window.just_changed = false;
         }
+        open_window.just_changed = false;
+
+        //Static planets don't orbit anything
+        let orbit =
+            is_dynamic.then(|| OsculatingOrbit::find(entity, &bodies, gravitational_constant));
 
         window
             .resizable([false; 2])
             .open(&mut open_window.is_open)
-            .show(context.ctx_mut().unwrap(), |ui| {
+            .show(&ctx, |ui| {
+                let dragging = ui.ctx().dragged_id().is_some();
+                let mut planet_query = params.p2();
+                let Ok((
+                    _,
+                    _,
+                    color_material,
+                    physics_transform,
+                    physics_velocity,
+                    physics_material,
+                    mut collider,
+                )) = planet_query.get_mut(entity)
+                else {
+                    return;
+                };
+
+                //Planets without physics can't be restored by undo
+                let snapshot = physics_material.as_deref().zip(physics_transform).map(
+                    |(material, transform)| {
+                        let color = color_material
+                            .and_then(|c| materials.get(&c.0))
+                            .map_or(Color::WHITE, |c| c.color);
+
+                        PlanetBuilder::from_components(
+                            material,
+                            transform,
+                            physics_velocity,
+                            collider.as_deref(),
+                            color,
+                        )
+                    },
+                );
+
                 egui::Grid::new("lol").show(ui, |ui| {
-                    if let Some(Some(material)) =
-                        color_material.map(|x| materials.get_mut(x.as_ref()))
-                    {
-                        material_color_editor_row(ui, material);
-                    }
-                    if let Some(mut physics_transform) = physics_transform {
-                        position_editor_row(ui, physics_transform.as_mut())
-                    }
-                    if let Some(mut physics_velocity) = physics_velocity {
-                        velocity_editor(ui, physics_velocity.as_mut());
+                    if let Some(material) = color_material.and_then(|c| materials.get_mut(&c.0)) {
+                        let before = material.color;
+
+                        if material_color_editor_row(ui, material) {
+                            history.record(
+                                ColorChange {
+                                    entity,
+                                    before,
+                                    after: material.color,
+                                },
+                                dragging,
+                            );
+                        }
                     }
                     if let Some(mut physics_material) = physics_material {
-                        mass_editor(ui, physics_material.as_mut());
-
-                        if let Some(mesh_handle) = mesh_handle {
-                            meshes.insert(
-                                mesh_handle.id(),
-                                Circle::new(calculate_radius(physics_material.mass))
-                                    .mesh()
-                                    .build(),
+                        if let Some(made_dynamic) = dynamic_editor_row(ui, is_dynamic) {
+                            set_dynamic(&mut commands, entity, made_dynamic);
+                            history.record(
+                                DynamicChange {
+                                    entity,
+                                    velocity: physics_velocity.copied().unwrap_or_default(),
+                                    made_dynamic,
+                                },
+                                false,
                             );
                         }
-                    }
-                    if ui.button("Despawn Entity").clicked() {
-                        commands.entity(entity).despawn();
+
+                        match collider.as_mut() {
+                            Some(collider) => shape_editor_rows(
+                                ui,
+                                entity,
+                                physics_material.as_mut(),
+                                collider,
+                                &mut history,
+                                dragging,
+                            ),
+                            None => record_change(
+                                &mut history,
+                                entity,
+                                physics_material.as_mut(),
+                                dragging,
+                                |material| mass_editor(ui, material),
+                            ),
+                        }
                     }
                 });
+
+                if let Ok(mut entity_mut) = params.p3().get_mut(entity) {
+                    registry.draw_sections(ui, &mut entity_mut, &mut history);
+                    draw_other_components(
+                        ui,
+                        &mut entity_mut,
+                        components,
+                        &type_registry,
+                        &registry,
+                        &mut history,
+                    );
+                }
+
+                if let Some(orbit) = &orbit {
+                    ui.collapsing("Orbit", |ui| {
+                        orbit_readout(ui, orbit.as_ref(), &mut open_window.show_orbit)
+                    });
+                }
+
+                if ui.button("Despawn Entity").clicked() {
+                    if let Some(planet) = snapshot {
+                        history.record(PlanetExistence::despawned(entity, planet), false);
+                    }
+
+                    commands.entity(entity).despawn();
+                }
             });
+
+        if let Ok((_, mut window, ..)) = params.p2().get_mut(entity) {
+            window.set_if_neq(open_window);
+        }
     }
 }
 
-fn try_edit_type<EditedType>(ui: &mut Ui, entity: &mut EntityMut)
-where
-    EditedType: EditableComponent,
-    EditedType: Component<Mutability = Mutable>,
-{
-    if let Some(mut to_be_edited) = entity.get_mut::<EditedType>() {
-        to_be_edited.edit_ui(ui)
+/// Returns whether the planet should become dynamic if the checkbox was toggled
+fn dynamic_editor_row(ui: &mut Ui, is_dynamic: bool) -> Option<bool> {
+    let mut dynamic = is_dynamic;
+
+    ui.label("Dynamic: ");
+    let changed = ui.checkbox(&mut dynamic, "").changed();
+    ui.end_row();
+
+    changed.then_some(dynamic)
+}
+
+/// Static planets have no velocity and leave no trail
+fn set_dynamic(commands: &mut Commands, entity: Entity, dynamic: bool) {
+    if dynamic {
+        commands
+            .entity(entity)
+            .insert((PhysicsVelocity::default(), Trail::default()));
+    } else {
+        commands.entity(entity).remove::<(PhysicsVelocity, Trail)>();
     }
 }
 
-fn material_color_editor_row(ui: &mut Ui, material: &mut ColorMaterial) {
-    let mut color = material.color.to_linear().to_f32_array();
+/// Mass, radius and density rows, the radius follows the mass unless it was set on its own
+///
+/// The collider is only touched when something changed, as that rebuilds the mesh
+fn shape_editor_rows(
+    ui: &mut Ui,
+    entity: Entity,
+    material: &mut PhysicsMaterial,
+    collider: &mut Mut<Collider>,
+    history: &mut EditHistory,
+    continuous: bool,
+) {
+    let before_material = *material;
+    let before_collider = **collider;
 
-    ui.label("Color");
-    ui.color_edit_button_rgba_premultiplied(&mut color);
+    let mut mass = material.mass;
+    let mut radius = collider.0.circle.radius;
+    let mut density = calculate_density(mass, radius);
+    let follows_mass = radius_follows_mass(radius, mass);
+
+    ui.label("mass: ");
+    let mass_changed = ui
+        .add(DragValue::new(&mut mass).range(0.0..=f32::INFINITY))
+        .changed();
     ui.end_row();
 
-    material.color = Color::LinearRgba(LinearRgba::from_f32_array(color))
-}
+    ui.label("Radius: ");
+    let (radius_changed, reset_radius) = ui
+        .horizontal(|ui| {
+            let changed = ui
+                .add(DragValue::new(&mut radius).range(0.0..=f32::INFINITY))
+                .changed();
+            let reset = ui
+                .add_enabled(!follows_mass, Button::new("Auto"))
+                .on_hover_text("Derive the radius from the mass")
+                .clicked();
+
+            (changed, reset)
+        })
+        .inner;
+    ui.end_row();
 
-fn position_editor_row(ui: &mut Ui, transform: &mut PhysicsTransform) {
-    ui.label("Position: ");
-    vec2_editor(ui, &mut transform.location);
+    ui.label("Density: ");
+    let density_changed = ui
+        .add(
+            DragValue::new(&mut density)
+                .range(0.0..=f32::INFINITY)
+                .speed(0.01),
+        )
+        .changed();
     ui.end_row();
+
+    if (mass_changed && follows_mass) || reset_radius {
+        radius = calculate_radius(mass);
+    }
+    //Density keeps the size and changes the mass
+    if density_changed {
+        mass = calculate_mass(radius, density);
+    }
+
+    if !(mass_changed || radius_changed || reset_radius || density_changed) {
+        return;
+    }
+
+    material.mass = mass;
+    collider.0.circle.radius = radius;
+
+    let mut group = CommandGroup::default();
+    group.push(ComponentChange {
+        entity,
+        before: before_material,
+        after: *material,
+    });
+    group.push(ComponentChange {
+        entity,
+        before: before_collider,
+        after: **collider,
+    });
+    history.record(group, continuous);
 }
 
-fn velocity_editor(ui: &mut Ui, velocity: &mut PhysicsVelocity) {
-    ui.label("Velocity: ");
-    vec2_editor(ui, &mut velocity.velocity);
+/// Runs an editor on a component and records the change in the undo history
+pub(super) fn record_change<C>(
+    history: &mut EditHistory,
+    entity: Entity,
+    component: &mut C,
+    continuous: bool,
+    editor: impl FnOnce(&mut C) -> bool,
+) where
+    C: Component + Clone,
+{
+    let before = component.clone();
+
+    if editor(component) {
+        history.record(
+            ComponentChange {
+                entity,
+                before,
+                after: component.clone(),
+            },
+            continuous,
+        );
+    }
+}
+
+fn material_color_editor_row(ui: &mut Ui, material: &mut ColorMaterial) -> bool {
+    let mut color = material.color.to_linear().to_f32_array();
+
+    ui.label("Color");
+    let changed = ui
+        .color_edit_button_rgba_premultiplied(&mut color)
+        .changed();
     ui.end_row();
+
+    if changed {
+        material.color = Color::LinearRgba(LinearRgba::from_f32_array(color))
+    }
+
+    changed
 }
 
-fn mass_editor(ui: &mut Ui, mass: &mut PhysicsMaterial) {
+fn mass_editor(ui: &mut Ui, mass: &mut PhysicsMaterial) -> bool {
     ui.label("mass: ");
-    ui.add(DragValue::new(&mut mass.mass));
+    let changed = ui
+        .add(DragValue::new(&mut mass.mass).range(0.0..=f32::INFINITY))
+        .changed();
     ui.end_row();
+
+    changed
 }
 
-pub fn vec2_editor(ui: &mut Ui, vec2: &mut Vec2) {
+/// Returns true if either coordinate was changed
+pub fn vec2_editor(ui: &mut Ui, vec2: &mut Vec2) -> bool {
     ui.horizontal(|ui| {
-        ui.add(DragValue::new(&mut vec2.x).prefix("x: "));
-        ui.add(DragValue::new(&mut vec2.y).prefix("y: "));
-    });
+        let x = ui.add(DragValue::new(&mut vec2.x).prefix("x: "));
+        let y = ui.add(DragValue::new(&mut vec2.y).prefix("y: "));
+
+        x.changed() || y.changed()
+    })
+    .inner
 }
@@ -0,0 +1,197 @@
+//! Runs the physics simulation without a window and writes the state of every body as csv
+//!
+//! Time advances by exactly one fixed step per update, so the output only depends
+//! on the level and the options and can be compared against golden files.
+
+use std::{io, time::Duration};
+
+use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
+
+use crate::{
+    level_builder::{LevelBuilder, LevelBuilderPlugin},
+    physics::{
+        PhysicsMaterial, PhysicsPlugin, PhysicsSettings, PhysicsTransform, PhysicsVelocity,
+        integrator::Integrator,
+    },
+};
+
+pub struct HeadlessOptions {
+    pub steps: u32,
+    pub hz: f64,
+    /// Write the state every n steps, the last step is always written
+    pub every: Option<u32>,
+    pub integrator: Integrator,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            steps: 1000,
+            hz: 64.0,
+            every: None,
+            integrator: Integrator::default(),
+        }
+    }
+}
+
+#[derive(States, Debug, PartialEq, Eq, Clone, Hash)]
+struct AlwaysOn;
+
+#[derive(Resource, Default)]
+struct StepCount(u32);
+
+/// Simulates the level for the given number of steps, writing a header followed by
+/// one line per body for every step that is written
+///
+/// The state before the first step isn't written, so 0 steps only writes the header
+pub fn run_headless(
+    options: &HeadlessOptions,
+    level: LevelBuilder,
+    output: &mut impl io::Write,
+) -> io::Result<()> {
+    let timestep = Duration::from_secs_f64(options.hz.recip());
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin))
+        .init_resource::<Assets<Mesh>>()
+        .init_resource::<Assets<ColorMaterial>>()
+        .add_plugins((
+            PhysicsPlugin {
+                running_state: AlwaysOn,
+                physics_settings: PhysicsSettings {
+                    integrator: options.integrator,
+                    ..Default::default()
+                },
+            },
+            LevelBuilderPlugin(level),
+        ))
+        .insert_state(AlwaysOn)
+        .insert_resource(Time::<Fixed>::from_duration(timestep))
+        //Every update advances time by exactly one fixed step, independent of the wall clock
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+        .init_resource::<StepCount>()
+        .add_systems(FixedUpdate, count_steps);
+
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_max_delta(timestep.max(Duration::from_millis(250)));
+
+    app.finish();
+    app.cleanup();
+
+    writeln!(output, "step,time,entity,mass,x,y,vx,vy")?;
+
+    let mut last_step = 0;
+    while last_step < options.steps {
+        app.update();
+
+        let step = app.world().resource::<StepCount>().0;
+        if step == last_step {
+            continue;
+        }
+        last_step = step;
+
+        if step == options.steps
+            || options
+                .every
+                .is_some_and(|n| n > 0 && step.is_multiple_of(n))
+        {
+            write_state(app.world_mut(), step, timestep, output)?;
+        }
+    }
+
+    output.flush()
+}
+
+fn count_steps(mut step_count: ResMut<StepCount>) {
+    step_count.0 += 1;
+}
+
+fn write_state(
+    world: &mut World,
+    step: u32,
+    timestep: Duration,
+    output: &mut impl io::Write,
+) -> io::Result<()> {
+    let mut query = world.query::<(
+        Entity,
+        &PhysicsMaterial,
+        &PhysicsTransform,
+        Option<&PhysicsVelocity>,
+    )>();
+
+    let mut bodies: Vec<_> = query.iter(world).collect();
+    bodies.sort_by_key(|(entity, ..)| entity.index());
+
+    let time = timestep.as_secs_f64() * step as f64;
+
+    for (entity, material, transform, velocity) in bodies {
+        let position = transform.location();
+        let velocity = velocity.map_or(Vec2::ZERO, |v| v.velocity());
+
+        writeln!(
+            output,
+            "{},{},{},{},{},{},{},{}",
+            step,
+            time,
+            entity.index(),
+            material.mass,
+            position.x,
+            position.y,
+            velocity.x,
+            velocity.y
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_builder::PlanetBuilder;
+
+    fn level() -> LevelBuilder {
+        LevelBuilder::default()
+            .add_planet(PlanetBuilder {
+                mass: 420000.0,
+                position: vec2(0.0, 0.0),
+                velocity: None,
+                ..Default::default()
+            })
+            .add_planet(PlanetBuilder {
+                mass: 4200.0,
+                position: vec2(1600.0, 0.0),
+                velocity: Some(vec2(0.0, 150.0)),
+                ..Default::default()
+            })
+            .add_planet(PlanetBuilder {
+                mass: 3700.0,
+                position: vec2(600.0, 300.0),
+                velocity: Some(vec2(-180.0, 230.0)),
+                ..Default::default()
+            })
+    }
+
+    fn run(options: &HeadlessOptions) -> String {
+        let mut output = Vec::new();
+        run_headless(options, level(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn runs_are_deterministic() {
+        let options = HeadlessOptions {
+            steps: 200,
+            every: Some(50),
+            ..Default::default()
+        };
+
+        let first = run(&options);
+
+        assert_eq!(first, run(&options));
+        //The header and three bodies at steps 50, 100, 150 and 200
+        assert_eq!(first.lines().count(), 1 + 3 * 4);
+        assert!(first.lines().last().unwrap().starts_with("200,"));
+    }
+}
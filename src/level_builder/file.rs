@@ -0,0 +1,366 @@
+//! Human editable level files in [RON](https://github.com/ron-rs/ron)
+//!
+//! ```ron
+//! (
+//!     gravitational_constant: 105.3, // Optional
+//!     units: Game, // Optional, one of Game, Si or Astronomical
+//!     planets: [
+//!         (
+//!             mass: 420000.0,
+//!             position: (0.0, 0.0),
+//!             velocity: (0.0, 0.0), // Defaults to (0.0, 0.0)
+//!             radius: 300.0, // Optional, derived from mass when missing
+//!             color: "#FFA500", // Optional, defaults to white
+//!             static: false, // Optional, static planets can't have a velocity
+//!         ),
+//!     ],
+//! )
+//! ```
+
+use std::{fmt::Display, fs, io, path::Path};
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use ron::{extensions::Extensions, ser::PrettyConfig};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::{LevelBuilder, PlanetBuilder};
+use crate::physics::units::UnitSystem;
+
+const PLANET_FIELDS: &[&str] = &["mass", "position", "velocity", "radius", "color", "static"];
+
+#[derive(Debug)]
+pub enum LevelFileError {
+    Io(io::Error),
+    /// The file is not valid RON or does not have the shape of a level
+    Syntax(ron::error::SpannedError),
+    /// A planet has a missing or invalid field
+    Planet {
+        index: usize,
+        field: String,
+        message: String,
+    },
+    Serialize(ron::Error),
+}
+
+impl Display for LevelFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelFileError::Io(e) => write!(f, "could not access level file: {}", e),
+            LevelFileError::Syntax(e) => write!(f, "invalid level file at {}", e),
+            LevelFileError::Planet {
+                index,
+                field,
+                message,
+            } if field.is_empty() => write!(f, "planets[{}]: {}", index, message),
+            LevelFileError::Planet {
+                index,
+                field,
+                message,
+            } => write!(f, "planets[{}].{}: {}", index, field, message),
+            LevelFileError::Serialize(e) => write!(f, "could not serialize level: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LevelFileError {}
+
+impl From<io::Error> for LevelFileError {
+    fn from(e: io::Error) -> Self {
+        LevelFileError::Io(e)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LevelFile<P> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gravitational_constant: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    units: Option<UnitSystem>,
+    planets: Vec<P>,
+}
+
+/// Only used for writing, planets are read field by field so errors can name the field
+#[derive(Serialize)]
+struct PlanetFile {
+    mass: f32,
+    position: (f32, f32),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    velocity: Option<(f32, f32)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    radius: Option<f32>,
+    color: String,
+    #[serde(rename = "static", skip_serializing_if = "is_false")]
+    is_static: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+fn ron_options() -> ron::Options {
+    ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME)
+}
+
+impl LevelBuilder {
+    pub fn from_ron(text: &str) -> Result<Self, LevelFileError> {
+        let file: LevelFile<ron::Value> = ron_options()
+            .from_str(text)
+            .map_err(LevelFileError::Syntax)?;
+
+        let planets = file
+            .planets
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| planet_from_value(index, value))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            planets,
+            gravitational_constant: file.gravitational_constant,
+            units: file.units,
+        })
+    }
+
+    pub fn to_ron(&self) -> Result<String, LevelFileError> {
+        let file = LevelFile {
+            gravitational_constant: self.gravitational_constant,
+            units: self.units,
+            planets: self.planets.iter().map(PlanetFile::from).collect(),
+        };
+
+        ron_options()
+            .to_string_pretty(&file, PrettyConfig::default())
+            .map_err(LevelFileError::Serialize)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LevelFileError> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LevelFileError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+}
+
+impl From<&PlanetBuilder> for PlanetFile {
+    fn from(planet: &PlanetBuilder) -> Self {
+        Self {
+            mass: planet.mass,
+            position: planet.position.into(),
+            velocity: planet.velocity.map(Into::into),
+            radius: planet.radius,
+            color: planet.color.to_srgba().to_hex(),
+            is_static: planet.velocity.is_none(),
+        }
+    }
+}
+
+fn planet_from_value(index: usize, value: ron::Value) -> Result<PlanetBuilder, LevelFileError> {
+    let error = |field: &str, message: &str| planet_error(index, field, message.to_string());
+
+    let ron::Value::Map(fields) = value else {
+        return Err(error(
+            "",
+            "expected a planet like (mass: 1.0, position: (0.0, 0.0))",
+        ));
+    };
+
+    let mut mass = None;
+    let mut position = None;
+    let mut velocity = None;
+    let mut is_static = false;
+    let mut planet = PlanetBuilder {
+        color: Color::WHITE,
+        ..Default::default()
+    };
+
+    for (key, value) in fields.iter() {
+        let ron::Value::String(key) = key else {
+            return Err(error("", &format!("invalid field name {:?}", key)));
+        };
+
+        match key.as_str() {
+            "mass" => mass = Some(non_negative(index, key, parse_field(index, key, value)?)?),
+            "position" => position = Some(parse_vec2(index, key, value)?),
+            "velocity" => velocity = Some(parse_vec2(index, key, value)?),
+            "radius" => {
+                planet.radius = Some(non_negative(index, key, parse_field(index, key, value)?)?)
+            }
+            "color" => {
+                let hex: String = parse_field(index, key, value)?;
+                planet.color = Srgba::hex(&hex)
+                    .map_err(|e| error(key, &format!("invalid hex color {:?}: {}", hex, e)))?
+                    .into();
+            }
+            "static" => is_static = parse_field(index, key, value)?,
+            unknown => {
+                return Err(error(
+                    unknown,
+                    &format!(
+                        "unknown field, expected one of {}",
+                        PLANET_FIELDS.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+
+    planet.mass = mass.ok_or_else(|| error("mass", "missing field"))?;
+    planet.position = position.ok_or_else(|| error("position", "missing field"))?;
+    planet.velocity = match (is_static, velocity) {
+        (true, Some(_)) => return Err(error("velocity", "static planets can't have a velocity")),
+        (true, None) => None,
+        (false, velocity) => Some(velocity.unwrap_or_default()),
+    };
+
+    Ok(planet)
+}
+
+fn planet_error(index: usize, field: &str, message: String) -> LevelFileError {
+    LevelFileError::Planet {
+        index,
+        field: field.to_string(),
+        message,
+    }
+}
+
+fn parse_field<T: DeserializeOwned>(
+    index: usize,
+    field: &str,
+    value: &ron::Value,
+) -> Result<T, LevelFileError> {
+    value
+        .clone()
+        .into_rust()
+        .map_err(|e| planet_error(index, field, e.to_string()))
+}
+
+fn parse_vec2(index: usize, field: &str, value: &ron::Value) -> Result<Vec2, LevelFileError> {
+    let vec2 = Vec2::from(parse_field::<(f32, f32)>(index, field, value)?);
+
+    if vec2.is_finite() {
+        Ok(vec2)
+    } else {
+        Err(planet_error(index, field, "must be finite".to_string()))
+    }
+}
+
+fn non_negative(index: usize, field: &str, value: f32) -> Result<f32, LevelFileError> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(value)
+    } else {
+        Err(planet_error(
+            index,
+            field,
+            format!("must be a finite non-negative number, got {}", value),
+        ))
+    }
+}
+
+/// A level loaded through the [`AssetServer`], from files ending in `.level.ron`
+#[derive(Asset, TypePath)]
+pub struct LevelAsset(pub LevelBuilder);
+
+#[derive(Default)]
+pub(super) struct LevelAssetLoader;
+
+impl AssetLoader for LevelAssetLoader {
+    type Asset = LevelAsset;
+    type Settings = ();
+    type Error = LevelFileError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let text = String::from_utf8(bytes)
+            .map_err(|e| LevelFileError::Io(io::Error::new(io::ErrorKind::InvalidData, e)))?;
+
+        Ok(LevelAsset(LevelBuilder::from_ron(&text)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ron_round_trip() {
+        let level = LevelBuilder::default()
+            .with_gravitational_constant(12.5)
+            .with_units(UnitSystem::Astronomical)
+            .add_planet(PlanetBuilder {
+                mass: 420000.0,
+                position: vec2(10.0, -20.0),
+                velocity: None,
+                radius: Some(300.0),
+                color: Color::srgb_u8(255, 165, 0),
+            })
+            .add_planet(PlanetBuilder {
+                mass: 10.0,
+                position: vec2(500.0, 0.0),
+                velocity: Some(vec2(0.0, 25.5)),
+                radius: None,
+                color: Color::WHITE,
+            });
+
+        let loaded = LevelBuilder::from_ron(&level.to_ron().unwrap()).unwrap();
+
+        assert_eq!(loaded.gravitational_constant, Some(12.5));
+        assert_eq!(loaded.units, Some(UnitSystem::Astronomical));
+        assert_eq!(loaded.planets.len(), level.planets.len());
+
+        for (loaded, planet) in loaded.planets.iter().zip(&level.planets) {
+            assert_eq!(loaded.mass, planet.mass);
+            assert_eq!(loaded.position, planet.position);
+            assert_eq!(loaded.velocity, planet.velocity);
+            assert_eq!(loaded.radius, planet.radius);
+            assert_eq!(
+                loaded.color.to_srgba().to_hex(),
+                planet.color.to_srgba().to_hex()
+            );
+        }
+    }
+
+    #[test]
+    fn bad_field_names_the_planet_and_field() {
+        let error = LevelBuilder::from_ron(
+            r#"(planets: [(mass: 1.0, position: (0.0, 0.0)), (mass: "heavy", position: (1.0, 0.0))])"#,
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            r#"planets[1].mass: Expected f32 but found the string "heavy" instead"#
+        );
+    }
+
+    #[test]
+    fn static_planets_cant_have_a_velocity() {
+        let error = LevelBuilder::from_ron(
+            "(planets: [(mass: 1.0, position: (0.0, 0.0), velocity: (1.0, 0.0), static: true)])",
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            "planets[0].velocity: static planets can't have a velocity"
+        );
+    }
+}
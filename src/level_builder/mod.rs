@@ -0,0 +1,462 @@
+use crate::{
+    edit_tools::{history::EditHistory, selection::Selection, window::EditorWindow},
+    graphics::trails::Trail,
+    physics::{
+        Collider, PendingSteps, PhysicsMaterial, PhysicsSettings, PhysicsTransform,
+        PhysicsVelocity,
+        diagnostics::PhysicsDiagnostics,
+        orbit::{OrbitDirection, OrbitalElements},
+        units::UnitSystem,
+    },
+};
+use bevy::{
+    asset::LoadState, ecs::system::SystemParam, math::bounding::BoundingCircle, prelude::*,
+};
+use file::LevelAssetLoader;
+use std::f32::consts::PI;
+
+mod file;
+
+pub use file::{LevelAsset, LevelFileError};
+
+const PLANET_DENSITY: f32 = 1.0;
+
+#[derive(Bundle, Clone)]
+pub(crate) struct DynamicPlanet {
+    physics_material: PhysicsMaterial,
+    transform: PhysicsTransform,
+    velocity: PhysicsVelocity,
+    #[bundle(ignore)]
+    style: PlanetStyle,
+}
+
+#[derive(Bundle)]
+pub(crate) struct StaticPlanet {
+    physics_material: PhysicsMaterial,
+    transform: PhysicsTransform,
+    #[bundle(ignore)]
+    style: PlanetStyle,
+}
+
+#[derive(Default, Clone)]
+struct PlanetStyle {
+    radius: f32,
+    color: Color,
+}
+
+pub(crate) enum SomePlanet {
+    DynamicPlanet(DynamicPlanet),
+    StaticPlanet(StaticPlanet),
+}
+
+impl SomePlanet {
+    pub(crate) fn build(
+        self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<ColorMaterial>,
+    ) -> Entity {
+        match self {
+            SomePlanet::DynamicPlanet(dynamic_planet) => {
+                let style: PlanetStyle = dynamic_planet.style.clone();
+
+                commands
+                    .spawn((
+                        dynamic_planet,
+                        Mesh2d(meshes.add(Circle::new(style.radius))),
+                        MeshMaterial2d(materials.add(style.color)),
+                        Collider(BoundingCircle {
+                            center: Vec2::ZERO,
+                            circle: Circle::new(style.radius),
+                        }),
+                        Trail::default(),
+                        EditorWindow::default(),
+                    ))
+                    .id()
+            }
+            SomePlanet::StaticPlanet(static_planet) => {
+                let style: PlanetStyle = static_planet.style.clone();
+
+                commands
+                    .spawn((
+                        static_planet,
+                        Mesh2d(meshes.add(Circle::new(style.radius))),
+                        MeshMaterial2d(materials.add(style.color)),
+                        Collider(BoundingCircle {
+                            center: Vec2::ZERO,
+                            circle: Circle::new(style.radius),
+                        }),
+                        EditorWindow::default(),
+                    ))
+                    .id()
+            }
+        }
+    }
+}
+
+///If not filled radius is derived from mass
+///
+///If given a none velocity the planet will be static
+///
+#[derive(Default, Clone)]
+pub struct PlanetBuilder {
+    pub mass: f32,
+    pub position: Vec2,
+    pub velocity: Option<Vec2>,
+    pub radius: Option<f32>,
+    pub color: Color,
+}
+
+impl PlanetBuilder {
+    /// Clamps a negative mass or radius to zero, for planets snapshotted from the
+    /// world where nothing stops either going negative
+    pub(crate) fn non_negative(mut self) -> Self {
+        self.mass = self.mass.max(0.0);
+        self.radius = self.radius.map(|radius| radius.max(0.0));
+        self
+    }
+
+    /// ## Panics
+    /// Panics if attempted to build when mass or radius are negative
+    pub(crate) fn build(self) -> SomePlanet {
+        assert!(!self.mass.is_sign_negative());
+
+        if let Some(r) = self.radius {
+            assert!(!r.is_sign_negative())
+        }
+
+        let physics_material = PhysicsMaterial { mass: self.mass };
+        let transform = PhysicsTransform {
+            location: self.position,
+        };
+
+        let style = PlanetStyle {
+            radius: self.radius.unwrap_or(calculate_radius(self.mass)),
+            color: self.color,
+        };
+
+        match self.velocity {
+            Some(velocity) => SomePlanet::DynamicPlanet(DynamicPlanet {
+                physics_material,
+                transform,
+                style,
+                velocity: PhysicsVelocity::new(velocity),
+            }),
+            None => SomePlanet::StaticPlanet(StaticPlanet {
+                physics_material,
+                transform,
+                style,
+            }),
+        }
+    }
+}
+
+impl PlanetBuilder {
+    /// Recreates the builder of an existing planet, the radius is only kept if
+    /// it differs from the one derived from mass
+    pub(crate) fn from_components(
+        material: &PhysicsMaterial,
+        transform: &PhysicsTransform,
+        velocity: Option<&PhysicsVelocity>,
+        collider: Option<&Collider>,
+        color: Color,
+    ) -> Self {
+        let radius = collider
+            .map(|c| c.0.circle.radius)
+            .filter(|r| !radius_follows_mass(*r, material.mass));
+
+        Self {
+            mass: material.mass,
+            position: transform.location,
+            velocity: velocity.map(|v| v.velocity),
+            radius,
+            color,
+        }
+    }
+
+    /// Places the planet on an orbit around the parent, relative to the parent's
+    /// position and velocity
+    ///
+    /// The gravitational constant must be in the same units as the planets, returns none
+    /// if the orbit has no position, see [`OrbitalElements::relative_state`]
+    pub fn orbiting(
+        mut self,
+        parent: &PlanetBuilder,
+        elements: &OrbitalElements,
+        gravitational_constant: f32,
+    ) -> Option<Self> {
+        //A static parent doesn't fall towards the planet, so only its own mass counts
+        let gravitational_parameter = match parent.velocity {
+            Some(_) => gravitational_constant * (parent.mass + self.mass),
+            None => gravitational_constant * parent.mass,
+        };
+        let (position, velocity) = elements.relative_state(gravitational_parameter)?;
+
+        self.position = parent.position + position;
+        self.velocity = Some(parent.velocity.unwrap_or_default() + velocity);
+        Some(self)
+    }
+
+    /// Keeps the position and sets the velocity for a circular orbit around the parent,
+    /// the planet is left as it is if it has no orbit
+    pub fn circular_orbit(
+        self,
+        parent: &PlanetBuilder,
+        direction: OrbitDirection,
+        gravitational_constant: f32,
+    ) -> Self {
+        let offset = self.position - parent.position;
+
+        if offset == Vec2::ZERO {
+            return self;
+        }
+
+        let elements = OrbitalElements::circular(offset.length(), offset.to_angle(), direction);
+        self.clone()
+            .orbiting(parent, &elements, gravitational_constant)
+            .unwrap_or(self)
+    }
+
+    /// Converts a planet given in the units of the unit system to game units
+    pub fn to_game_units(self, units: UnitSystem) -> Self {
+        let scale = units.scale();
+
+        Self {
+            mass: self.mass * scale.mass,
+            position: self.position * scale.length,
+            velocity: self.velocity.map(|v| v * scale.velocity()),
+            radius: self.radius.map(|r| r * scale.length),
+            color: self.color,
+        }
+    }
+}
+
+pub(crate) fn calculate_radius(mass: f32) -> f32 {
+    (mass.sqrt() / PI.sqrt()) / PLANET_DENSITY
+}
+
+/// False if the radius was set independently of the mass
+pub(crate) fn radius_follows_mass(radius: f32, mass: f32) -> bool {
+    (radius - calculate_radius(mass)).abs() <= 1e-3
+}
+
+/// Mass per area of a planet
+pub(crate) fn calculate_density(mass: f32, radius: f32) -> f32 {
+    if radius > 0.0 {
+        mass / (PI * radius.powi(2))
+    } else {
+        0.0
+    }
+}
+
+pub(crate) fn calculate_mass(radius: f32, density: f32) -> f32 {
+    density * PI * radius.powi(2)
+}
+
+/// If units are set the planets are given in those units and the gravitational
+/// constant defaults to the real one of the unit system
+///
+/// If neither units nor a gravitational constant are set the level keeps the
+/// gravitational constant of the [`PhysicsSettings`]
+#[derive(Default, Resource, Clone)]
+pub struct LevelBuilder {
+    planets: Vec<PlanetBuilder>,
+    gravitational_constant: Option<f32>,
+    units: Option<UnitSystem>,
+}
+
+pub struct LevelBuilderPlugin(pub LevelBuilder);
+
+impl Plugin for LevelBuilderPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.0.clone());
+        app.add_systems(Startup, construct_level_system);
+    }
+}
+
+/// Loads a level through the [`AssetServer`] and builds it once loaded, requires the [`AssetPlugin`]
+///
+/// The path is of a `.level.ron` file relative to the assets folder
+pub struct LevelAssetPlugin(pub String);
+
+impl Plugin for LevelAssetPlugin {
+    fn build(&self, app: &mut App) {
+        let path = self.0.clone();
+
+        app.init_asset::<LevelAsset>()
+            .init_asset_loader::<LevelAssetLoader>()
+            .add_systems(
+                Startup,
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    commands.insert_resource(PendingLevel(asset_server.load(&path)));
+                },
+            )
+            .add_systems(
+                Update,
+                construct_loaded_level_system.run_if(resource_exists::<PendingLevel>),
+            );
+    }
+}
+
+/// The level asset that is built as soon as it finishes loading
+#[derive(Resource)]
+struct PendingLevel(Handle<LevelAsset>);
+
+impl LevelBuilder {
+    pub fn add_planet(mut self, planet: PlanetBuilder) -> Self {
+        self.planets.push(planet);
+        self
+    }
+
+    /// The gravitational constant, given in the units of the level
+    pub fn with_gravitational_constant(mut self, gravitational_constant: f32) -> Self {
+        self.gravitational_constant = Some(gravitational_constant);
+        self
+    }
+
+    pub fn with_units(mut self, units: UnitSystem) -> Self {
+        self.units = Some(units);
+        self
+    }
+
+    /// The planets of the level converted to game units
+    pub fn planets(&self) -> impl Iterator<Item = PlanetBuilder> + '_ {
+        let units = self.units.unwrap_or_default();
+
+        self.planets
+            .iter()
+            .map(move |p| p.clone().to_game_units(units))
+    }
+
+    /// The gravitational constant of the level in game units, if the level sets one
+    pub fn gravitational_constant(&self) -> Option<f32> {
+        match (self.units, self.gravitational_constant) {
+            (Some(units), Some(g)) => Some(units.scale().gravitational_constant(g)),
+            (Some(units), None) => Some(units.gravitational_constant()),
+            (None, g) => g,
+        }
+    }
+}
+
+fn construct_level_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    level: Res<LevelBuilder>,
+    mut physics_settings: Option<ResMut<PhysicsSettings>>,
+) {
+    construct_level(
+        &level,
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        physics_settings.as_deref_mut(),
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn construct_loaded_level_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut levels: ResMut<Assets<LevelAsset>>,
+    pending_level: Res<PendingLevel>,
+    asset_server: Res<AssetServer>,
+    mut physics_settings: Option<ResMut<PhysicsSettings>>,
+    diagnostics: Option<ResMut<PhysicsDiagnostics>>,
+) {
+    match asset_server.load_state(&pending_level.0) {
+        LoadState::Loaded => {
+            commands.remove_resource::<PendingLevel>();
+
+            let Some(LevelAsset(level)) = levels.remove(&pending_level.0) else {
+                return;
+            };
+
+            construct_level(
+                &level,
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                physics_settings.as_deref_mut(),
+            );
+            commands.insert_resource(level);
+
+            if let Some(mut diagnostics) = diagnostics {
+                diagnostics.clear_baseline();
+            }
+        }
+        LoadState::Failed(e) => {
+            error!("Could not load level: {}", e);
+            commands.remove_resource::<PendingLevel>();
+        }
+        LoadState::NotLoaded | LoadState::Loading => {}
+    }
+}
+
+/// Swaps the planets in the world for the ones of another level
+#[derive(SystemParam)]
+pub(crate) struct LevelReplacer<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    planet_query: Query<'w, 's, Entity, With<PhysicsMaterial>>,
+    physics_settings: Option<ResMut<'w, PhysicsSettings>>,
+    history: Option<ResMut<'w, EditHistory>>,
+    selection: Option<ResMut<'w, Selection>>,
+    pending_steps: Option<ResMut<'w, PendingSteps>>,
+    diagnostics: Option<ResMut<'w, PhysicsDiagnostics>>,
+}
+
+impl LevelReplacer<'_, '_> {
+    /// Despawns every planet and builds the level, which becomes the one the
+    /// ```reset``` command goes back to
+    ///
+    /// Edits, the selection and pending steps refer to the old planets so they
+    /// are dropped, and energy drift is measured from the new level
+    pub(crate) fn replace(&mut self, level: LevelBuilder) {
+        for entity in self.planet_query.iter() {
+            self.commands.entity(entity).despawn();
+        }
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        if let Some(selection) = &mut self.selection {
+            selection.clear();
+        }
+        if let Some(pending_steps) = &mut self.pending_steps {
+            pending_steps.0 = 0;
+        }
+        if let Some(diagnostics) = &mut self.diagnostics {
+            diagnostics.clear_baseline();
+        }
+
+        construct_level(
+            &level,
+            &mut self.commands,
+            &mut self.meshes,
+            &mut self.materials,
+            self.physics_settings.as_deref_mut(),
+        );
+        self.commands.insert_resource(level);
+    }
+}
+
+/// Spawns the planets of the level, the level is kept so it can be built again
+pub(crate) fn construct_level(
+    level: &LevelBuilder,
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    physics_settings: Option<&mut PhysicsSettings>,
+) {
+    if let Some(physics_settings) = physics_settings
+        && let Some(gravitational_constant) = level.gravitational_constant()
+    {
+        physics_settings.gravitational_constant = gravitational_constant;
+    }
+
+    for planet in level.planets() {
+        planet.build().build(commands, meshes, materials);
+    }
+}
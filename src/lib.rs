@@ -1,6 +1,7 @@
 pub mod commands;
 pub mod edit_tools;
 pub mod graphics;
+pub mod headless;
 pub mod level_builder;
 pub mod mouse_camera_control;
 pub mod physics;
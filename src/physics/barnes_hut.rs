@@ -0,0 +1,278 @@
+use bevy::prelude::*;
+
+use super::ForceLaw;
+use super::integrator::Body;
+
+/// Prevents infinite subdivision when bodies share the exact same position
+const MAX_DEPTH: usize = 32;
+
+/// A quadtree over the bodies where every node stores the total mass and
+/// the center of mass of the bodies within it
+struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    center: Vec2,
+    half_size: f32,
+    mass: f32,
+    center_of_mass: Vec2,
+    children: Option<[usize; 4]>,
+    bodies: Vec<usize>,
+}
+
+impl Node {
+    fn new(center: Vec2, half_size: f32) -> Self {
+        Self {
+            center,
+            half_size,
+            mass: 0.0,
+            center_of_mass: Vec2::ZERO,
+            children: None,
+            bodies: Vec::new(),
+        }
+    }
+
+    fn contains(&self, position: Vec2) -> bool {
+        (position - self.center).abs().max_element() <= self.half_size
+    }
+
+    fn quadrant(&self, position: Vec2) -> usize {
+        (position.x >= self.center.x) as usize + 2 * (position.y >= self.center.y) as usize
+    }
+}
+
+impl QuadTree {
+    fn new(bodies: &[Body]) -> Self {
+        let min = bodies
+            .iter()
+            .fold(Vec2::INFINITY, |min, body| min.min(body.position));
+        let max = bodies
+            .iter()
+            .fold(Vec2::NEG_INFINITY, |max, body| max.max(body.position));
+
+        let center = (min + max) * 0.5;
+        let half_size = ((max - min).max_element() * 0.5).max(f32::EPSILON);
+
+        let mut tree = Self {
+            nodes: vec![Node::new(center, half_size)],
+        };
+
+        for i in 0..bodies.len() {
+            tree.insert(0, i, bodies, 0);
+        }
+
+        tree.calculate_mass(0, bodies);
+        tree
+    }
+
+    fn insert(&mut self, node: usize, body: usize, bodies: &[Body], depth: usize) {
+        if let Some(children) = self.nodes[node].children {
+            let quadrant = self.nodes[node].quadrant(bodies[body].position);
+            self.insert(children[quadrant], body, bodies, depth + 1);
+            return;
+        }
+
+        if self.nodes[node].bodies.is_empty() || depth >= MAX_DEPTH {
+            self.nodes[node].bodies.push(body);
+            return;
+        }
+
+        self.subdivide(node);
+
+        for existing in std::mem::take(&mut self.nodes[node].bodies) {
+            self.insert(node, existing, bodies, depth);
+        }
+        self.insert(node, body, bodies, depth);
+    }
+
+    fn subdivide(&mut self, node: usize) {
+        let Node {
+            center, half_size, ..
+        } = self.nodes[node];
+        let quarter = half_size * 0.5;
+
+        let children = [
+            vec2(-quarter, -quarter),
+            vec2(quarter, -quarter),
+            vec2(-quarter, quarter),
+            vec2(quarter, quarter),
+        ]
+        .map(|offset| {
+            self.nodes.push(Node::new(center + offset, quarter));
+            self.nodes.len() - 1
+        });
+
+        self.nodes[node].children = Some(children);
+    }
+
+    fn calculate_mass(&mut self, node: usize, bodies: &[Body]) {
+        let (mass, weighted_position) = match self.nodes[node].children {
+            Some(children) => children.iter().fold((0.0, Vec2::ZERO), |acc, &child| {
+                self.calculate_mass(child, bodies);
+                let child = &self.nodes[child];
+                (
+                    acc.0 + child.mass,
+                    acc.1 + child.center_of_mass * child.mass,
+                )
+            }),
+            None => self.nodes[node]
+                .bodies
+                .iter()
+                .map(|&i| &bodies[i])
+                .fold((0.0, Vec2::ZERO), |acc, body| {
+                    (acc.0 + body.mass, acc.1 + body.position * body.mass)
+                }),
+        };
+
+        let node = &mut self.nodes[node];
+        node.mass = mass;
+        if mass > 0.0 {
+            node.center_of_mass = weighted_position / mass;
+        }
+    }
+
+    fn acceleration(
+        &self,
+        node: usize,
+        body: usize,
+        bodies: &[Body],
+        theta: f32,
+        force_law: &ForceLaw,
+    ) -> Vec2 {
+        let node = &self.nodes[node];
+        let position = bodies[body].position;
+
+        if node.mass <= 0.0 {
+            return Vec2::ZERO;
+        }
+
+        match node.children {
+            None => node
+                .bodies
+                .iter()
+                .filter(|&&other| other != body)
+                .map(|&other| {
+                    force_law.acceleration(bodies[other].mass, bodies[other].position - position)
+                })
+                .sum(),
+            Some(children) => {
+                let distance = node.center_of_mass.distance(position);
+
+                //A large theta could otherwise let a body pull on itself
+                if !node.contains(position) && node.half_size * 2.0 < theta * distance {
+                    force_law.acceleration(node.mass, node.center_of_mass - position)
+                } else {
+                    children
+                        .iter()
+                        .map(|&child| self.acceleration(child, body, bodies, theta, force_law))
+                        .sum()
+                }
+            }
+        }
+    }
+}
+
+/// Approximates the gravitational acceleration on every body using a Barnes-Hut quadtree
+///
+/// Groups of bodies are treated as a single point mass when the size of their
+/// node divided by its distance is smaller than `theta`. A theta of 0 gives the same
+/// result as the brute force sum while larger values are faster but less accurate
+pub(crate) fn barnes_hut_accelerations(
+    bodies: &[Body],
+    theta: f32,
+    force_law: &ForceLaw,
+) -> Vec<Vec2> {
+    if bodies.is_empty() {
+        return Vec::new();
+    }
+
+    let tree = QuadTree::new(bodies);
+
+    (0..bodies.len())
+        .map(|i| tree.acceleration(0, i, bodies, theta, force_law))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+    use crate::physics::gravity_accelerations;
+
+    fn random_cluster(count: usize) -> Vec<Body> {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        (0..count)
+            .map(|_| Body {
+                mass: rng.random_range(1.0..100.0),
+                position: vec2(
+                    rng.random_range(-1000.0..1000.0),
+                    rng.random_range(-1000.0..1000.0),
+                ),
+                velocity: Vec2::ZERO,
+                acceleration: Vec2::ZERO,
+                is_dynamic: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn barnes_hut_matches_brute_force() {
+        let bodies = random_cluster(300);
+
+        let exact = gravity_accelerations(&bodies, &ForceLaw::default());
+        let approximate = barnes_hut_accelerations(&bodies, 0.5, &ForceLaw::default());
+
+        let error: f32 = exact
+            .iter()
+            .zip(&approximate)
+            .map(|(a, b)| a.distance(*b))
+            .sum();
+        let magnitude: f32 = exact.iter().map(|a| a.length()).sum();
+
+        assert!(
+            error / magnitude < 0.01,
+            "relative error {} is too large",
+            error / magnitude
+        );
+    }
+
+    #[test]
+    fn large_theta_skips_the_body_itself() {
+        let bodies: Vec<Body> = [
+            vec2(-1.0, -1.0),
+            vec2(1.0, -1.0),
+            vec2(-1.0, 1.0),
+            vec2(1.0, 1.0),
+        ]
+        .map(|position| Body {
+            mass: 10.0,
+            position: position * 500.0,
+            velocity: Vec2::ZERO,
+            acceleration: Vec2::ZERO,
+            is_dynamic: true,
+        })
+        .into();
+
+        let exact = gravity_accelerations(&bodies, &ForceLaw::default());
+        let approximate = barnes_hut_accelerations(&bodies, 2.0, &ForceLaw::default());
+
+        for (a, b) in exact.iter().zip(&approximate) {
+            assert!(a.distance(*b) <= a.length() * 1e-3);
+        }
+    }
+
+    #[test]
+    fn zero_theta_is_exact() {
+        let bodies = random_cluster(100);
+
+        let exact = gravity_accelerations(&bodies, &ForceLaw::default());
+        let approximate = barnes_hut_accelerations(&bodies, 0.0, &ForceLaw::default());
+
+        for (a, b) in exact.iter().zip(&approximate) {
+            assert!(a.distance(*b) <= a.length() * 1e-3);
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use super::{
+    Collider, Frozen, PhysicsMaterial, PhysicsSettings, PhysicsTransform, PhysicsVelocity,
+};
+use crate::level_builder::PlanetBuilder;
+
+const FRAGMENT_COUNT: usize = 4;
+/// Fragments lighter than this are merged instead
+const MIN_FRAGMENT_MASS: f32 = 1.0;
+/// Fraction of the impact speed the fragments fly apart with
+const FRAGMENT_SPREAD: f32 = 0.3;
+
+/// How two overlapping bodies are resolved
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionMode {
+    /// Bodies pass through each other
+    Ignore,
+    /// Perfectly inelastic, the lighter body is absorbed conserving mass and momentum
+    #[default]
+    Merge,
+    /// Perfectly elastic bounce
+    Bounce,
+    /// The lighter body shatters into smaller dynamic planets
+    Fragment,
+}
+
+/// Overrides [`PhysicsSettings::collision_mode`] for a single body
+///
+/// If both bodies in a collision have a policy the heavier one decides
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CollisionPolicy(pub CollisionMode);
+
+/// The mode a collision between two bodies is resolved with, given their mass and policy
+///
+/// The policy of the heavier body decides, the first body counts as the heavier one on a tie
+pub(crate) fn pair_collision_mode(
+    default: CollisionMode,
+    (mass1, policy1): (f32, Option<CollisionMode>),
+    (mass2, policy2): (f32, Option<CollisionMode>),
+) -> CollisionMode {
+    let (heavier, lighter) = if mass1 >= mass2 {
+        (policy1, policy2)
+    } else {
+        (policy2, policy1)
+    };
+
+    heavier.or(lighter).unwrap_or(default)
+}
+
+/// Written after a collision between two bodies has been resolved
+///
+/// When merging or fragmenting the despawn of the second body has been queued on
+/// [`Commands`], it still exists until they are applied
+#[derive(Message, Clone, Copy, Debug)]
+pub struct PlanetCollision {
+    /// The body that survived a merge is first, it is the heavier one unless only
+    /// the lighter one is static. Otherwise the heavier body is first.
+    pub entities: [Entity; 2],
+    pub mode: CollisionMode,
+    pub position: Vec2,
+    pub relative_speed: f32,
+}
+
+/// Snapshot of a colliding entity so collisions can be resolved without
+/// juggling multiple mutable query items
+#[derive(Clone, Copy)]
+struct ContactBody {
+    entity: Entity,
+    mass: f32,
+    position: Vec2,
+    velocity: Vec2,
+    radius: f32,
+    color: Color,
+    is_dynamic: bool,
+    policy: Option<CollisionMode>,
+    changed: bool,
+    removed: bool,
+}
+
+impl ContactBody {
+    fn inverse_mass(&self) -> f32 {
+        if self.is_dynamic && self.mass > 0.0 {
+            self.mass.recip()
+        } else {
+            0.0
+        }
+    }
+
+    fn overlaps(&self, other: &ContactBody) -> bool {
+        self.position.distance_squared(other.position) < (self.radius + other.radius).powi(2)
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn resolve_collisions(
+    settings: Res<PhysicsSettings>,
+    mut query: Query<
+        (
+            Entity,
+            &mut PhysicsMaterial,
+            &mut PhysicsTransform,
+            Option<&mut PhysicsVelocity>,
+            &mut Collider,
+            Option<&CollisionPolicy>,
+            Option<&MeshMaterial2d<ColorMaterial>>,
+        ),
+        Without<Frozen>,
+    >,
+    mut collision_ew: MessageWriter<PlanetCollision>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut bodies: Vec<ContactBody> = query
+        .iter()
+        .map(
+            |(entity, material, transform, velocity, collider, policy, color)| ContactBody {
+                entity,
+                mass: material.mass,
+                position: transform.location,
+                velocity: velocity.map_or(Vec2::ZERO, |v| v.velocity),
+                radius: collider.0.circle.radius,
+                color: color
+                    .and_then(|c| materials.get(c))
+                    .map_or(Color::WHITE, |c| c.color),
+                is_dynamic: velocity.is_some(),
+                policy: policy.map(|p| p.0),
+                changed: false,
+                removed: false,
+            },
+        )
+        .collect();
+
+    let mut fragments = Vec::new();
+
+    for (i, j) in find_contacts(&bodies) {
+        let (heavier, lighter) = if bodies[i].mass >= bodies[j].mass {
+            (i, j)
+        } else {
+            (j, i)
+        };
+
+        let mode = pair_collision_mode(
+            settings.collision_mode,
+            (bodies[i].mass, bodies[i].policy),
+            (bodies[j].mass, bodies[j].policy),
+        );
+
+        //Earlier collisions this step may have moved or removed either body
+        if mode == CollisionMode::Ignore
+            || bodies[heavier].removed
+            || bodies[lighter].removed
+            || !bodies[heavier].overlaps(&bodies[lighter])
+        {
+            continue;
+        }
+
+        let Ok([h, l]) = bodies.get_disjoint_mut([heavier, lighter]) else {
+            continue;
+        };
+
+        let relative_speed = (h.velocity - l.velocity).length();
+        let position = h.position + (l.position - h.position).normalize_or_zero() * h.radius;
+        let mut entities = [h.entity, l.entity];
+
+        match mode {
+            CollisionMode::Fragment if l.mass / FRAGMENT_COUNT as f32 >= MIN_FRAGMENT_MASS => {
+                fragments.extend(fragment(h, l, relative_speed));
+            }
+            CollisionMode::Merge | CollisionMode::Fragment => {
+                //Static bodies always survive a merge so levels keep their anchors
+                if h.is_dynamic && !l.is_dynamic {
+                    merge(l, h);
+                    entities.reverse();
+                } else {
+                    merge(h, l);
+                }
+            }
+            CollisionMode::Bounce => bounce(h, l),
+            CollisionMode::Ignore => unreachable!(),
+        }
+
+        collision_ew.write(PlanetCollision {
+            entities,
+            mode,
+            position,
+            relative_speed,
+        });
+    }
+
+    for body in bodies.iter().filter(|b| b.changed) {
+        if body.removed {
+            commands.entity(body.entity).despawn();
+            continue;
+        }
+
+        let Ok((_, mut material, mut transform, velocity, mut collider, _, _)) =
+            query.get_mut(body.entity)
+        else {
+            continue;
+        };
+
+        material.mass = body.mass;
+        transform.location = body.position;
+        collider.0.center = body.position;
+
+        if let Some(mut velocity) = velocity {
+            velocity.velocity = body.velocity;
+        }
+
+        //The mesh follows the collider
+        collider.0.circle.radius = body.radius;
+    }
+
+    for fragment in fragments {
+        fragment
+            .build()
+            .build(&mut commands, &mut meshes, &mut materials);
+    }
+}
+
+/// Finds all overlapping pairs where at least one body can move
+fn find_contacts(bodies: &[ContactBody]) -> Vec<(usize, usize)> {
+    let mut contacts = Vec::new();
+
+    for (i, body1) in bodies.iter().enumerate() {
+        for (j, body2) in bodies.iter().enumerate().skip(i + 1) {
+            if (body1.is_dynamic || body2.is_dynamic) && body1.overlaps(body2) {
+                contacts.push((i, j));
+            }
+        }
+    }
+
+    contacts
+}
+
+fn merge(survivor: &mut ContactBody, absorbed: &mut ContactBody) {
+    let total_mass = survivor.mass + absorbed.mass;
+
+    if survivor.is_dynamic && total_mass > 0.0 {
+        survivor.position =
+            (survivor.position * survivor.mass + absorbed.position * absorbed.mass) / total_mass;
+        survivor.velocity =
+            (survivor.velocity * survivor.mass + absorbed.velocity * absorbed.mass) / total_mass;
+    }
+
+    survivor.mass = total_mass;
+    //Adding up the areas keeps a radius set apart from the mass, and gives the
+    //radius of the total mass otherwise
+    survivor.radius = survivor.radius.hypot(absorbed.radius);
+    survivor.changed = true;
+
+    absorbed.removed = true;
+    absorbed.changed = true;
+}
+
+fn bounce(body1: &mut ContactBody, body2: &mut ContactBody) {
+    let inverse_mass1 = body1.inverse_mass();
+    let inverse_mass2 = body2.inverse_mass();
+    let total_inverse_mass = inverse_mass1 + inverse_mass2;
+
+    if total_inverse_mass <= 0.0 {
+        return;
+    }
+
+    let normal = (body2.position - body1.position).normalize_or(Vec2::X);
+
+    //Push the bodies apart so they don't collide again next step
+    let overlap = body1.radius + body2.radius - body1.position.distance(body2.position);
+    body1.position -= normal * overlap * inverse_mass1 / total_inverse_mass;
+    body2.position += normal * overlap * inverse_mass2 / total_inverse_mass;
+
+    let approach_speed = (body1.velocity - body2.velocity).dot(normal);
+
+    if approach_speed > 0.0 {
+        let impulse = 2.0 * approach_speed / total_inverse_mass;
+
+        body1.velocity -= normal * impulse * inverse_mass1;
+        body2.velocity += normal * impulse * inverse_mass2;
+    }
+
+    body1.changed = true;
+    body2.changed = true;
+}
+
+/// Shatters the lighter body into a ring of fragments just outside the heavier one
+///
+/// Both bodies end up moving with their common center of mass velocity
+/// and the fragments spread out symmetrically so momentum is conserved
+fn fragment(
+    heavier: &mut ContactBody,
+    lighter: &mut ContactBody,
+    relative_speed: f32,
+) -> Vec<PlanetBuilder> {
+    let base_velocity = if heavier.is_dynamic {
+        (heavier.velocity * heavier.mass + lighter.velocity * lighter.mass)
+            / (heavier.mass + lighter.mass)
+    } else {
+        Vec2::ZERO
+    };
+
+    let normal = (lighter.position - heavier.position).normalize_or(Vec2::X);
+    let ring_center = heavier.position + normal * (heavier.radius + 2.0 * lighter.radius);
+    let fragment_mass = lighter.mass / FRAGMENT_COUNT as f32;
+
+    heavier.velocity = base_velocity;
+    heavier.changed = true;
+
+    lighter.removed = true;
+    lighter.changed = true;
+
+    (0..FRAGMENT_COUNT)
+        .map(|i| {
+            let direction = Vec2::from_angle(TAU * i as f32 / FRAGMENT_COUNT as f32).rotate(normal);
+
+            PlanetBuilder {
+                mass: fragment_mass,
+                position: ring_center + direction * lighter.radius,
+                velocity: Some(base_velocity + direction * relative_speed * FRAGMENT_SPREAD),
+                radius: None,
+                color: lighter.color,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_builder::calculate_radius;
+
+    fn body(mass: f32, position: Vec2, velocity: Vec2, is_dynamic: bool) -> ContactBody {
+        ContactBody {
+            entity: Entity::PLACEHOLDER,
+            mass,
+            position,
+            velocity,
+            radius: calculate_radius(mass),
+            color: Color::WHITE,
+            is_dynamic,
+            policy: None,
+            changed: false,
+            removed: false,
+        }
+    }
+
+    fn kinetic_energy(bodies: &[&ContactBody]) -> f32 {
+        bodies
+            .iter()
+            .map(|b| 0.5 * b.mass * b.velocity.length_squared())
+            .sum()
+    }
+
+    #[test]
+    fn merge_conserves_mass_and_momentum() {
+        let mut survivor = body(300.0, vec2(0.0, 0.0), vec2(10.0, -5.0), true);
+        let mut absorbed = body(100.0, vec2(8.0, 2.0), vec2(-40.0, 20.0), true);
+        let momentum = survivor.mass * survivor.velocity + absorbed.mass * absorbed.velocity;
+
+        merge(&mut survivor, &mut absorbed);
+
+        assert_eq!(survivor.mass, 400.0);
+        assert!((survivor.mass * survivor.velocity - momentum).length() < 1e-3);
+        assert!((survivor.radius - calculate_radius(400.0)).abs() < 1e-3);
+        assert!(absorbed.removed);
+        assert!(!survivor.removed);
+    }
+
+    #[test]
+    fn merge_scales_an_overridden_radius_by_area() {
+        let mut survivor = body(300.0, Vec2::ZERO, Vec2::ZERO, true);
+        survivor.radius = 30.0;
+        let mut absorbed = body(100.0, vec2(8.0, 2.0), Vec2::ZERO, true);
+        absorbed.radius = 40.0;
+
+        merge(&mut survivor, &mut absorbed);
+
+        assert!((survivor.radius - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn merge_into_static_body_keeps_it_in_place() {
+        let mut survivor = body(100.0, vec2(0.0, 0.0), Vec2::ZERO, false);
+        let mut absorbed = body(300.0, vec2(8.0, 2.0), vec2(-40.0, 20.0), true);
+
+        merge(&mut survivor, &mut absorbed);
+
+        assert_eq!(survivor.mass, 400.0);
+        assert_eq!(survivor.position, Vec2::ZERO);
+        assert!(absorbed.removed);
+    }
+
+    #[test]
+    fn bounce_conserves_kinetic_energy_and_momentum() {
+        let mut body1 = body(300.0, vec2(0.0, 0.0), vec2(30.0, 10.0), true);
+        let mut body2 = body(100.0, vec2(12.0, 4.0), vec2(-20.0, 5.0), true);
+        let energy = kinetic_energy(&[&body1, &body2]);
+        let momentum = body1.mass * body1.velocity + body2.mass * body2.velocity;
+
+        bounce(&mut body1, &mut body2);
+
+        assert!((kinetic_energy(&[&body1, &body2]) - energy).abs() < 1e-3 * energy);
+        assert!(
+            (body1.mass * body1.velocity + body2.mass * body2.velocity - momentum).length()
+                < 1e-3 * momentum.length()
+        );
+        //The bodies now move apart
+        assert!((body2.velocity - body1.velocity).dot(body2.position - body1.position) > 0.0);
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+
+use super::{Frozen, PhysicsMaterial, PhysicsSettings, PhysicsTransform, PhysicsVelocity};
+
+/// Conserved quantities of the whole simulation, updated every physics step
+///
+/// Useful for checking that the simulation is numerically healthy, a good
+/// integrator keeps the drifts close to zero as long as nothing collides
+#[derive(Resource, Default, Clone, Debug)]
+pub struct PhysicsDiagnostics {
+    pub kinetic_energy: f32,
+    pub potential_energy: f32,
+    pub linear_momentum: Vec2,
+    /// Around the origin, positive is counterclockwise
+    pub angular_momentum: f32,
+    pub center_of_mass: Vec2,
+    pub total_mass: f32,
+    baseline: Option<ConservedQuantities>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ConservedQuantities {
+    total_energy: f32,
+    linear_momentum: Vec2,
+    angular_momentum: f32,
+}
+
+impl PhysicsDiagnostics {
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy + self.potential_energy
+    }
+
+    /// Relative change in total energy since the baseline
+    pub fn energy_drift(&self) -> f32 {
+        self.baseline.map_or(0.0, |b| {
+            relative_drift(self.total_energy() - b.total_energy, b.total_energy)
+        })
+    }
+
+    /// Change in linear momentum since the baseline, relative to the starting momentum
+    pub fn momentum_drift(&self) -> f32 {
+        self.baseline.map_or(0.0, |b| {
+            relative_drift(
+                (self.linear_momentum - b.linear_momentum).length(),
+                b.linear_momentum.length(),
+            )
+        })
+    }
+
+    /// Relative change in angular momentum since the baseline
+    pub fn angular_momentum_drift(&self) -> f32 {
+        self.baseline.map_or(0.0, |b| {
+            relative_drift(
+                self.angular_momentum - b.angular_momentum,
+                b.angular_momentum,
+            )
+        })
+    }
+
+    /// Makes the current state the one drift is measured against
+    pub fn reset_baseline(&mut self) {
+        self.baseline = Some(self.conserved_quantities());
+    }
+
+    /// Makes the state of the next update the one drift is measured against, for when
+    /// the bodies are replaced and the current state is about to be stale
+    pub fn clear_baseline(&mut self) {
+        self.baseline = None;
+    }
+
+    fn conserved_quantities(&self) -> ConservedQuantities {
+        ConservedQuantities {
+            total_energy: self.total_energy(),
+            linear_momentum: self.linear_momentum,
+            angular_momentum: self.angular_momentum,
+        }
+    }
+
+    /// Every value as lines of text, printed by the ```diagnostics``` dev command
+    pub fn summary(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Energy: kinetic {:.3}, potential {:.3}, total {:.3} (drift {:+.3e})",
+                self.kinetic_energy,
+                self.potential_energy,
+                self.total_energy(),
+                self.energy_drift()
+            ),
+            format!(
+                "Momentum: linear {} (drift {:+.3e}), angular {:.3} (drift {:+.3e})",
+                self.linear_momentum,
+                self.momentum_drift(),
+                self.angular_momentum,
+                self.angular_momentum_drift()
+            ),
+            format!(
+                "Center of mass: {}, total mass {:.3}",
+                self.center_of_mass, self.total_mass
+            ),
+        ]
+    }
+
+    /// Prints every value into the log
+    pub fn log(&self) {
+        for line in self.summary() {
+            info!("{}", line);
+        }
+    }
+}
+
+/// Drift relative to the baseline, falls back to the absolute change if the baseline is zero
+fn relative_drift(change: f32, baseline: f32) -> f32 {
+    if baseline.abs() > f32::EPSILON {
+        change / baseline.abs()
+    } else {
+        change
+    }
+}
+
+pub(super) fn update_diagnostics(
+    settings: Res<PhysicsSettings>,
+    mut diagnostics: ResMut<PhysicsDiagnostics>,
+    query: Query<
+        (
+            &PhysicsMaterial,
+            &PhysicsTransform,
+            Option<&PhysicsVelocity>,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    let force_law = settings.force_law();
+
+    let mut kinetic_energy = 0.0;
+    let mut potential_energy = 0.0;
+    let mut linear_momentum = Vec2::ZERO;
+    let mut angular_momentum = 0.0;
+    let mut total_mass = 0.0;
+    let mut weighted_position = Vec2::ZERO;
+
+    for (material, transform, velocity) in query.iter() {
+        let mass = material.mass;
+        let velocity = velocity.map_or(Vec2::ZERO, |v| v.velocity);
+
+        kinetic_energy += 0.5 * mass * velocity.length_squared();
+        linear_momentum += mass * velocity;
+        angular_momentum += mass * transform.location.perp_dot(velocity);
+        total_mass += mass;
+        weighted_position += mass * transform.location;
+    }
+
+    for [(material1, transform1, _), (material2, transform2, _)] in query.iter_combinations() {
+        potential_energy += force_law.potential_energy(
+            material1.mass,
+            material2.mass,
+            transform2.location - transform1.location,
+        );
+    }
+
+    diagnostics.kinetic_energy = kinetic_energy;
+    diagnostics.potential_energy = potential_energy;
+    diagnostics.linear_momentum = linear_momentum;
+    diagnostics.angular_momentum = angular_momentum;
+    diagnostics.total_mass = total_mass;
+    diagnostics.center_of_mass = if total_mass > 0.0 {
+        weighted_position / total_mass
+    } else {
+        Vec2::ZERO
+    };
+
+    //A level loaded as an asset spawns its bodies after the first updates
+    if diagnostics.baseline.is_none() && !query.is_empty() {
+        diagnostics.reset_baseline();
+    }
+}
@@ -0,0 +1,308 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use bevy::prelude::*;
+
+/// Numerical scheme used to advance the simulation by one fixed step
+///
+/// Ordered roughly by cost, the cheaper integrators need less force
+/// evaluations per step but drift more over long simulations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Integrator {
+    /// First order symplectic euler, one force evaluation per step
+    #[default]
+    SemiImplicitEuler,
+    /// Second order symplectic drift-kick-drift leapfrog, one force evaluation per step
+    Leapfrog,
+    /// Second order symplectic kick-drift-kick integrator, one force evaluation per step
+    ///
+    /// Reuses the acceleration from the previous step so the first step
+    /// after spawning or editing a body is less accurate
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta, four force evaluations per step
+    ///
+    /// Not symplectic, so energy slowly drifts but it's very accurate for short timescales
+    Rk4,
+}
+
+#[derive(Debug)]
+pub struct ParseIntegratorError(String);
+
+impl Display for ParseIntegratorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown integrator {}, expected one of euler, leapfrog, verlet or rk4",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseIntegratorError {}
+
+impl FromStr for Integrator {
+    type Err = ParseIntegratorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "euler" => Ok(Integrator::SemiImplicitEuler),
+            "leapfrog" => Ok(Integrator::Leapfrog),
+            "verlet" => Ok(Integrator::VelocityVerlet),
+            "rk4" => Ok(Integrator::Rk4),
+            _ => Err(ParseIntegratorError(s.to_string())),
+        }
+    }
+}
+
+/// A snapshot of a single physics entity the integrators operate on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Body {
+    pub mass: f32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub acceleration: Vec2,
+    /// Static bodies attract others but are never moved
+    pub is_dynamic: bool,
+}
+
+impl Body {
+    pub fn is_finite(&self) -> bool {
+        self.mass.is_finite()
+            && self.position.is_finite()
+            && self.velocity.is_finite()
+            && self.acceleration.is_finite()
+    }
+}
+
+impl Integrator {
+    /// Advances all dynamic bodies by delta seconds
+    ///
+    /// `accelerations` should return the acceleration for every body in the
+    /// given slice, in the same order
+    pub(crate) fn step<F>(self, bodies: &mut [Body], delta: f32, accelerations: F)
+    where
+        F: Fn(&[Body]) -> Vec<Vec2>,
+    {
+        match self {
+            Integrator::SemiImplicitEuler => {
+                let new_accelerations = accelerations(bodies);
+
+                for (body, acceleration) in dynamic_bodies(bodies, new_accelerations) {
+                    body.acceleration = acceleration;
+                    body.velocity += acceleration * delta;
+                    body.position += body.velocity * delta;
+                }
+            }
+            Integrator::Leapfrog => {
+                drift(bodies, delta * 0.5);
+                let new_accelerations = accelerations(bodies);
+
+                for (body, acceleration) in dynamic_bodies(bodies, new_accelerations) {
+                    body.acceleration = acceleration;
+                    body.velocity += acceleration * delta;
+                }
+                drift(bodies, delta * 0.5);
+            }
+            Integrator::VelocityVerlet => {
+                for body in bodies.iter_mut().filter(|b| b.is_dynamic) {
+                    body.position +=
+                        body.velocity * delta + 0.5 * body.acceleration * delta.powi(2);
+                }
+
+                let new_accelerations = accelerations(bodies);
+
+                for (body, acceleration) in dynamic_bodies(bodies, new_accelerations) {
+                    body.velocity += 0.5 * (body.acceleration + acceleration) * delta;
+                    body.acceleration = acceleration;
+                }
+            }
+            Integrator::Rk4 => rk4_step(bodies, delta, accelerations),
+        }
+    }
+}
+
+fn rk4_step<F>(bodies: &mut [Body], delta: f32, accelerations: F)
+where
+    F: Fn(&[Body]) -> Vec<Vec2>,
+{
+    let v1: Vec<Vec2> = bodies.iter().map(|b| b.velocity).collect();
+    let a1 = accelerations(bodies);
+
+    let v2 = offset_velocities(bodies, &a1, delta * 0.5);
+    let a2 = accelerations(&offset_positions(bodies, &v1, delta * 0.5));
+
+    let v3 = offset_velocities(bodies, &a2, delta * 0.5);
+    let a3 = accelerations(&offset_positions(bodies, &v2, delta * 0.5));
+
+    let v4 = offset_velocities(bodies, &a3, delta);
+    let a4 = accelerations(&offset_positions(bodies, &v3, delta));
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+        if !body.is_dynamic {
+            continue;
+        }
+
+        body.position += (v1[i] + 2.0 * v2[i] + 2.0 * v3[i] + v4[i]) * delta / 6.0;
+        body.velocity += (a1[i] + 2.0 * a2[i] + 2.0 * a3[i] + a4[i]) * delta / 6.0;
+        body.acceleration = a1[i];
+    }
+}
+
+fn dynamic_bodies(
+    bodies: &mut [Body],
+    accelerations: Vec<Vec2>,
+) -> impl Iterator<Item = (&mut Body, Vec2)> {
+    bodies
+        .iter_mut()
+        .zip(accelerations)
+        .filter(|(body, _)| body.is_dynamic)
+}
+
+fn drift(bodies: &mut [Body], delta: f32) {
+    for body in bodies.iter_mut().filter(|b| b.is_dynamic) {
+        body.position += body.velocity * delta;
+    }
+}
+
+/// Returns a copy of the bodies with the dynamic ones moved by `velocities * delta`
+fn offset_positions(bodies: &[Body], velocities: &[Vec2], delta: f32) -> Vec<Body> {
+    bodies
+        .iter()
+        .zip(velocities)
+        .map(|(body, velocity)| {
+            let mut body = *body;
+            if body.is_dynamic {
+                body.position += *velocity * delta;
+            }
+            body
+        })
+        .collect()
+}
+
+fn offset_velocities(bodies: &[Body], accelerations: &[Vec2], delta: f32) -> Vec<Vec2> {
+    bodies
+        .iter()
+        .zip(accelerations)
+        .map(|(body, acceleration)| body.velocity + *acceleration * delta)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{ForceLaw, gravity_accelerations};
+
+    const FORCE_LAW: ForceLaw = ForceLaw {
+        gravitational_constant: 1.0,
+        softening: 0.0,
+    };
+    const CENTRAL_MASS: f32 = 1000.0;
+    const RADIUS: f32 = 100.0;
+    const DELTA: f32 = 1.0;
+
+    /// A light body on a circular orbit around a static one
+    fn circular_orbit() -> Vec<Body> {
+        let speed = (FORCE_LAW.gravitational_constant * CENTRAL_MASS / RADIUS).sqrt();
+
+        let mut bodies = vec![
+            Body {
+                mass: CENTRAL_MASS,
+                position: Vec2::ZERO,
+                velocity: Vec2::ZERO,
+                acceleration: Vec2::ZERO,
+                is_dynamic: false,
+            },
+            Body {
+                mass: 1.0,
+                position: vec2(RADIUS, 0.0),
+                velocity: vec2(0.0, speed),
+                acceleration: Vec2::ZERO,
+                is_dynamic: true,
+            },
+        ];
+
+        //Velocity Verlet reuses the previous acceleration
+        bodies[1].acceleration = accelerations(&bodies)[1];
+        bodies
+    }
+
+    fn accelerations(bodies: &[Body]) -> Vec<Vec2> {
+        gravity_accelerations(bodies, &FORCE_LAW)
+    }
+
+    fn energy(bodies: &[Body]) -> f32 {
+        let orbiting = &bodies[1];
+
+        0.5 * orbiting.mass * orbiting.velocity.length_squared()
+            + FORCE_LAW.potential_energy(
+                CENTRAL_MASS,
+                orbiting.mass,
+                orbiting.position - bodies[0].position,
+            )
+    }
+
+    fn period() -> f32 {
+        std::f32::consts::TAU
+            * (RADIUS.powi(3) / (FORCE_LAW.gravitational_constant * CENTRAL_MASS)).sqrt()
+    }
+
+    /// Largest relative change in energy over a number of orbits
+    fn max_energy_drift(integrator: Integrator, orbits: f32) -> f32 {
+        let mut bodies = circular_orbit();
+        let initial_energy = energy(&bodies);
+        let steps = (orbits * period() / DELTA) as usize;
+
+        (0..steps)
+            .map(|_| {
+                integrator.step(&mut bodies, DELTA, accelerations);
+                ((energy(&bodies) - initial_energy) / initial_energy).abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Distance from where the body should be after a whole number of orbits
+    fn position_error(integrator: Integrator, orbits: usize) -> f32 {
+        let mut bodies = circular_orbit();
+        let start = bodies[1].position;
+        let steps = (orbits as f32 * period() / DELTA).round() as usize;
+        //The orbit doesn't take a whole number of steps, finish it with a shorter one
+        let remainder = orbits as f32 * period() - steps as f32 * DELTA;
+
+        for _ in 0..steps {
+            integrator.step(&mut bodies, DELTA, accelerations);
+        }
+        integrator.step(&mut bodies, remainder, accelerations);
+
+        bodies[1].position.distance(start)
+    }
+
+    #[test]
+    fn symplectic_integrators_keep_energy_bounded() {
+        for integrator in [Integrator::Leapfrog, Integrator::VelocityVerlet] {
+            let short = max_energy_drift(integrator, 1.0);
+            let long = max_energy_drift(integrator, 20.0);
+
+            assert!(long < 1e-2, "{:?} drifted by {}", integrator, long);
+            //The error oscillates instead of growing with every orbit
+            assert!(
+                long < 2.0 * short + 1e-4,
+                "{:?} drifted by {} over one orbit and {} over twenty",
+                integrator,
+                short,
+                long
+            );
+        }
+    }
+
+    #[test]
+    fn rk4_is_more_accurate_than_euler() {
+        let rk4 = position_error(Integrator::Rk4, 1);
+        let euler = position_error(Integrator::SemiImplicitEuler, 1);
+
+        assert!(
+            rk4 * 10.0 < euler,
+            "rk4 was off by {} and euler by {}",
+            rk4,
+            euler
+        );
+    }
+}
@@ -0,0 +1,496 @@
+mod barnes_hut;
+pub mod collision;
+pub mod diagnostics;
+pub mod integrator;
+pub mod orbit;
+pub(crate) mod prediction;
+pub mod units;
+
+use std::cell::RefCell;
+
+use bevy::math::bounding::BoundingCircle;
+use bevy::{color::palettes::css::LIGHT_BLUE, prelude::*};
+use bevy_egui::egui::DragValue;
+
+use crate::edit_tools::EditableComponent;
+use crate::edit_tools::window::vec2_editor;
+use barnes_hut::barnes_hut_accelerations;
+use collision::{CollisionMode, PlanetCollision, resolve_collisions};
+use diagnostics::{PhysicsDiagnostics, update_diagnostics};
+use integrator::{Body, Integrator};
+
+/// Tuned for the default 64hz fixed timestep, this used to be 6740.0 with the
+/// timestep applied twice when integrating
+pub const DEFAULT_GRAVITATIONAL_CONSTANT: f32 = 6740.0 / 64.0;
+
+pub struct PhysicsPlugin<S: States> {
+    pub running_state: S,
+    pub physics_settings: PhysicsSettings,
+}
+
+/// Running state of the simulation, used as the running state of the [`PhysicsPlugin`]
+/// so the dev commands can pause and resume it
+#[derive(States, Debug, Default, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SimulationState {
+    #[default]
+    Running,
+    Paused,
+}
+
+impl SimulationState {
+    pub fn toggled(self) -> Self {
+        match self {
+            SimulationState::Running => SimulationState::Paused,
+            SimulationState::Paused => SimulationState::Running,
+        }
+    }
+}
+
+/// Fixed updates the physics run for while the running state is not active,
+/// one is used up every fixed update
+#[derive(Resource, Default)]
+pub struct PendingSteps(pub u32);
+
+#[derive(Resource, Clone, PartialEq)]
+pub struct PhysicsSettings {
+    pub gravitational_constant: f32,
+    pub integrator: Integrator,
+    pub solver: GravitySolver,
+    pub collision_mode: CollisionMode,
+    /// Plummer softening length, keeps the force finite when bodies get close
+    pub softening: f32,
+    pub non_finite_action: NonFiniteAction,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravitational_constant: DEFAULT_GRAVITATIONAL_CONSTANT,
+            integrator: Default::default(),
+            solver: Default::default(),
+            collision_mode: Default::default(),
+            softening: 0.0,
+            non_finite_action: Default::default(),
+        }
+    }
+}
+
+impl PhysicsSettings {
+    pub(crate) fn force_law(&self) -> ForceLaw {
+        ForceLaw {
+            gravitational_constant: self.gravitational_constant,
+            softening: self.softening,
+        }
+    }
+}
+
+/// What to do with a body whose physics state stopped being finite
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFiniteAction {
+    /// Keep the body at its last finite state and exclude it from the simulation
+    #[default]
+    Freeze,
+    /// Despawn the body
+    Remove,
+}
+
+/// Written when a body's physics state became NaN or infinite
+///
+/// The bad state is never written back so the rest of the simulation stays intact
+#[derive(Message, Clone, Copy, Debug)]
+pub struct NonFiniteBody {
+    pub entity: Entity,
+}
+
+/// Bodies with this component are ignored by the simulation
+///
+/// Removing it puts the body back into the simulation, the `unfreeze` dev command
+/// does so for bodies whose state is finite again.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Frozen;
+
+/// The parameters of the gravitational force shared by all solvers
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ForceLaw {
+    pub gravitational_constant: f32,
+    pub softening: f32,
+}
+
+impl Default for ForceLaw {
+    fn default() -> Self {
+        PhysicsSettings::default().force_law()
+    }
+}
+
+impl ForceLaw {
+    /// Acceleration caused by an attractor at the given separation from the body
+    fn acceleration(&self, attractor_mass: f32, separation: Vec2) -> Vec2 {
+        let distance_squared = separation.length_squared() + self.softening.powi(2);
+
+        if distance_squared <= 0.0 {
+            return Vec2::ZERO;
+        }
+
+        self.gravitational_constant * attractor_mass * separation / distance_squared.powf(1.5)
+    }
+
+    /// Gravitational potential energy between two bodies, consistent with the softening
+    fn potential_energy(&self, mass1: f32, mass2: f32, separation: Vec2) -> f32 {
+        let distance = (separation.length_squared() + self.softening.powi(2)).sqrt();
+
+        if distance <= 0.0 {
+            return 0.0;
+        }
+
+        -self.gravitational_constant * mass1 * mass2 / distance
+    }
+}
+
+/// The algorithm used to sum up the gravitational forces between bodies
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum GravitySolver {
+    /// Exact pairwise sum, O(n²)
+    #[default]
+    BruteForce,
+    /// Approximation using a quadtree, O(n log n)
+    ///
+    /// Theta is the opening angle, 0.5 is a good default and 0 is exact
+    BarnesHut { theta: f32 },
+}
+
+impl GravitySolver {
+    pub(crate) fn accelerations(self, bodies: &[Body], force_law: &ForceLaw) -> Vec<Vec2> {
+        match self {
+            GravitySolver::BruteForce => gravity_accelerations(bodies, force_law),
+            GravitySolver::BarnesHut { theta } => {
+                barnes_hut_accelerations(bodies, theta, force_law)
+            }
+        }
+    }
+}
+
+impl<S: States> Plugin for PhysicsPlugin<S> {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.physics_settings.clone());
+        app.add_message::<PlanetCollision>();
+        app.add_message::<NonFiniteBody>();
+        app.init_resource::<PendingSteps>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                (
+                    calculate_physics,
+                    resolve_collisions,
+                    handle_non_finite_bodies,
+                )
+                    .chain()
+                    .run_if(in_state(self.running_state.clone()).or(steps_pending)),
+                use_pending_step.run_if(steps_pending),
+            )
+                .chain(),
+        );
+        //Diagnostics keep updating while paused so edits show up immediately
+        app.init_resource::<PhysicsDiagnostics>();
+        app.add_systems(
+            FixedUpdate,
+            update_diagnostics.after(handle_non_finite_bodies),
+        );
+        app.add_systems(
+            Update,
+            (
+                move_physics_entities_visual,
+                resize_planet_meshes,
+                //Headless apps don't have gizmos
+                draw_acceleration_arrows.run_if(resource_exists::<GizmoConfigStore>),
+            ),
+        );
+    }
+}
+
+fn steps_pending(pending_steps: Res<PendingSteps>) -> bool {
+    pending_steps.0 > 0
+}
+
+fn use_pending_step(mut pending_steps: ResMut<PendingSteps>) {
+    pending_steps.0 -= 1;
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Collider(pub BoundingCircle);
+
+#[derive(Component, Clone, Copy)]
+pub struct PhysicsTransform {
+    pub(crate) location: Vec2,
+}
+
+impl PhysicsTransform {
+    pub fn new(location: Vec2) -> Self {
+        Self { location }
+    }
+
+    pub fn location(&self) -> Vec2 {
+        self.location
+    }
+
+    pub fn move_by_velocity(&mut self, velocity: Vec2, delta: f32) {
+        self.location += velocity * delta
+    }
+}
+
+impl EditableComponent for PhysicsTransform {
+    const SECTION: &'static str = "Physics";
+
+    fn edit_ui(&mut self, ui: &mut bevy_egui::egui::Ui) -> bool {
+        ui.label("Position: ");
+        let changed = vec2_editor(ui, &mut self.location);
+        ui.end_row();
+
+        changed
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+pub struct PhysicsMaterial {
+    pub mass: f32,
+}
+
+impl EditableComponent for PhysicsMaterial {
+    fn edit_ui(&mut self, ui: &mut bevy_egui::egui::Ui) -> bool {
+        ui.label("mass: ");
+        let changed = ui.add(DragValue::new(&mut self.mass)).changed();
+        ui.end_row();
+
+        changed
+    }
+}
+
+#[derive(Component, Clone, Copy, Default)]
+pub struct PhysicsVelocity {
+    pub(crate) velocity: Vec2,
+    acceleration: Vec2,
+}
+
+impl PhysicsVelocity {
+    pub fn new(velocity: Vec2) -> Self {
+        Self {
+            velocity,
+            ..Default::default()
+        }
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        self.velocity
+    }
+}
+
+impl EditableComponent for PhysicsVelocity {
+    const SECTION: &'static str = "Physics";
+    const ORDER: i32 = 1;
+
+    fn edit_ui(&mut self, ui: &mut bevy_egui::egui::Ui) -> bool {
+        ui.label("Velocity: ");
+        let changed = vec2_editor(ui, &mut self.velocity);
+        ui.end_row();
+
+        changed
+    }
+}
+
+impl Body {
+    pub(crate) fn from_components(
+        material: &PhysicsMaterial,
+        transform: &PhysicsTransform,
+        velocity: Option<&PhysicsVelocity>,
+    ) -> Self {
+        Self {
+            mass: material.mass,
+            position: transform.location,
+            velocity: velocity.map_or(Vec2::ZERO, |v| v.velocity),
+            acceleration: velocity.map_or(Vec2::ZERO, |v| v.acceleration),
+            is_dynamic: velocity.is_some(),
+        }
+    }
+}
+
+fn calculate_physics(
+    time: Res<Time>,
+    settings: Res<PhysicsSettings>,
+    mut non_finite_ew: MessageWriter<NonFiniteBody>,
+    mut query: Query<
+        (
+            Entity,
+            &PhysicsMaterial,
+            &mut PhysicsTransform,
+            Option<&mut PhysicsVelocity>,
+        ),
+        Without<Frozen>,
+    >,
+) {
+    let delta = time.delta_secs();
+
+    let mut entities = Vec::new();
+    let mut bodies = Vec::new();
+
+    for (entity, material, transform, velocity) in query.iter() {
+        let body = Body::from_components(material, transform, velocity);
+
+        //Bodies which were corrupted elsewhere are kept out of the simulation
+        if body.is_finite() {
+            entities.push(entity);
+            bodies.push(body);
+        } else {
+            non_finite_ew.write(NonFiniteBody { entity });
+        }
+    }
+
+    let force_law = settings.force_law();
+    //Bodies which stopped being finite part way through the step
+    let non_finite = RefCell::new(vec![false; bodies.len()]);
+
+    settings.integrator.step(&mut bodies, delta, |bodies| {
+        finite_accelerations(
+            settings.solver,
+            bodies,
+            &force_law,
+            &mut non_finite.borrow_mut(),
+        )
+    });
+
+    for ((entity, body), non_finite) in entities.into_iter().zip(bodies).zip(non_finite.take()) {
+        if non_finite || !body.is_finite() {
+            non_finite_ew.write(NonFiniteBody { entity });
+            continue;
+        }
+
+        let Ok((_, _, mut t, Some(mut v))) = query.get_mut(entity) else {
+            continue;
+        };
+
+        t.location = body.position;
+        v.velocity = body.velocity;
+        v.acceleration = body.acceleration;
+    }
+}
+
+/// The accelerations of the solver with non-finite values kept from spreading
+///
+/// Bodies in a non-finite state don't pull on the others, they and bodies whose
+/// acceleration isn't finite are marked in `non_finite` and get no acceleration.
+fn finite_accelerations(
+    solver: GravitySolver,
+    bodies: &[Body],
+    force_law: &ForceLaw,
+    non_finite: &mut [bool],
+) -> Vec<Vec2> {
+    let finite: Vec<usize> = (0..bodies.len())
+        .filter(|&i| bodies[i].is_finite())
+        .collect();
+
+    let mut accelerations = if finite.len() == bodies.len() {
+        solver.accelerations(bodies, force_law)
+    } else {
+        let finite_bodies: Vec<Body> = finite.iter().map(|&i| bodies[i]).collect();
+        let mut accelerations = vec![Vec2::NAN; bodies.len()];
+
+        for (i, acceleration) in finite
+            .into_iter()
+            .zip(solver.accelerations(&finite_bodies, force_law))
+        {
+            accelerations[i] = acceleration;
+        }
+
+        accelerations
+    };
+
+    for (acceleration, non_finite) in accelerations.iter_mut().zip(non_finite) {
+        if !acceleration.is_finite() {
+            *acceleration = Vec2::ZERO;
+            *non_finite = true;
+        }
+    }
+
+    accelerations
+}
+
+fn draw_acceleration_arrows(
+    mut gizmos: Gizmos,
+    query: Query<(&PhysicsTransform, &PhysicsVelocity)>,
+) {
+    for (transform, velocity) in query.iter() {
+        gizmos.arrow_2d(
+            transform.location,
+            transform.location + velocity.acceleration,
+            LIGHT_BLUE,
+        );
+    }
+}
+
+fn handle_non_finite_bodies(
+    settings: Res<PhysicsSettings>,
+    mut non_finite_er: MessageReader<NonFiniteBody>,
+    mut commands: Commands,
+) {
+    for NonFiniteBody { entity } in non_finite_er.read() {
+        match settings.non_finite_action {
+            NonFiniteAction::Freeze => {
+                error!(
+                    "Entity {} has a non-finite physics state, freezing it",
+                    entity
+                );
+                commands.entity(*entity).try_insert(Frozen);
+            }
+            NonFiniteAction::Remove => {
+                error!(
+                    "Entity {} has a non-finite physics state, removing it",
+                    entity
+                );
+                commands.entity(*entity).try_despawn();
+            }
+        }
+    }
+}
+
+/// Computes the gravitational acceleration on every body by summing over all pairs
+fn gravity_accelerations(bodies: &[Body], force_law: &ForceLaw) -> Vec<Vec2> {
+    let mut accelerations = vec![Vec2::ZERO; bodies.len()];
+
+    for (i, body1) in bodies.iter().enumerate() {
+        for (j, body2) in bodies.iter().enumerate().skip(i + 1) {
+            let separation = body2.position - body1.position;
+
+            accelerations[i] += force_law.acceleration(body2.mass, separation);
+            accelerations[j] -= force_law.acceleration(body1.mass, separation);
+        }
+    }
+
+    accelerations
+}
+
+fn move_physics_entities_visual(
+    mut query: Query<(&mut Transform, &PhysicsTransform, Option<&mut Collider>)>,
+) {
+    for (mut transform, physics_transform, mut collider) in query.iter_mut() {
+        transform.translation.x = physics_transform.location.x;
+        transform.translation.y = physics_transform.location.y;
+
+        //Only the radius counts as a change, the center follows the body every frame
+        if let Some(collider) = collider.as_mut() {
+            collider.bypass_change_detection().0.center = physics_transform.location;
+        }
+    }
+}
+
+/// Keeps planet meshes the size of their collider
+fn resize_planet_meshes(
+    query: Query<(&Collider, &Mesh2d), Changed<Collider>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (collider, mesh) in query.iter() {
+        if let Err(e) = meshes.insert(
+            mesh.id(),
+            Circle::new(collider.0.circle.radius).mesh().build(),
+        ) {
+            warn!("Could not resize planet mesh: {}", e);
+        }
+    }
+}
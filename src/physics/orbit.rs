@@ -0,0 +1,270 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+/// Which way a body goes around its parent
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OrbitDirection {
+    #[default]
+    Counterclockwise,
+    Clockwise,
+}
+
+impl OrbitDirection {
+    fn sign(self) -> f32 {
+        match self {
+            OrbitDirection::Counterclockwise => 1.0,
+            OrbitDirection::Clockwise => -1.0,
+        }
+    }
+}
+
+/// A two body orbit, angles are in radians
+///
+/// Unbound orbits have an eccentricity of 1 or more and a negative semi-major axis
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f32,
+    /// 0 is a circle, below 1 an ellipse
+    pub eccentricity: f32,
+    /// Angle from the x axis to the closest approach
+    pub argument_of_periapsis: f32,
+    /// Angle from the closest approach to the body
+    pub true_anomaly: f32,
+    pub direction: OrbitDirection,
+}
+
+impl Default for OrbitalElements {
+    fn default() -> Self {
+        Self {
+            semi_major_axis: 500.0,
+            eccentricity: 0.0,
+            argument_of_periapsis: 0.0,
+            true_anomaly: 0.0,
+            direction: OrbitDirection::default(),
+        }
+    }
+}
+
+impl OrbitalElements {
+    pub fn circular(radius: f32, angle: f32, direction: OrbitDirection) -> Self {
+        Self {
+            semi_major_axis: radius,
+            eccentricity: 0.0,
+            argument_of_periapsis: angle,
+            true_anomaly: 0.0,
+            direction,
+        }
+    }
+
+    /// The osculating orbit of a body, from its position and velocity relative to the parent
+    ///
+    /// `gravitational_parameter` is the gravitational constant times the mass of both bodies,
+    /// or only the parent's mass if it is static. Returns none if the bodies overlap or
+    /// nothing attracts
+    pub fn from_relative_state(
+        position: Vec2,
+        velocity: Vec2,
+        gravitational_parameter: f32,
+    ) -> Option<Self> {
+        let distance = position.length();
+
+        if distance <= 0.0 || gravitational_parameter <= 0.0 {
+            return None;
+        }
+
+        let specific_energy = 0.5 * velocity.length_squared() - gravitational_parameter / distance;
+        let angular_momentum = position.perp_dot(velocity);
+
+        let eccentricity_vector =
+            ((velocity.length_squared() - gravitational_parameter / distance) * position
+                - position.dot(velocity) * velocity)
+                / gravitational_parameter;
+        let eccentricity = eccentricity_vector.length();
+
+        let direction = if angular_momentum >= 0.0 {
+            OrbitDirection::Counterclockwise
+        } else {
+            OrbitDirection::Clockwise
+        };
+
+        //Circular orbits have no periapsis, measure from the body instead
+        let argument_of_periapsis = if eccentricity > 1e-6 {
+            eccentricity_vector.to_angle()
+        } else {
+            position.to_angle()
+        };
+        //Clockwise orbits are mirrored, so their anomaly runs the other way
+        let true_anomaly =
+            direction.sign() * Vec2::from_angle(argument_of_periapsis).angle_to(position);
+
+        Some(Self {
+            semi_major_axis: -gravitational_parameter / (2.0 * specific_energy),
+            eccentricity,
+            argument_of_periapsis,
+            true_anomaly,
+            direction,
+        })
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.eccentricity < 1.0
+    }
+
+    pub fn semi_latus_rectum(&self) -> f32 {
+        self.semi_major_axis * (1.0 - self.eccentricity.powi(2))
+    }
+
+    /// Closest distance to the parent
+    pub fn periapsis(&self) -> f32 {
+        self.semi_latus_rectum() / (1.0 + self.eccentricity)
+    }
+
+    /// Furthest distance from the parent, none for unbound orbits
+    pub fn apoapsis(&self) -> Option<f32> {
+        self.is_bound()
+            .then(|| self.semi_latus_rectum() / (1.0 - self.eccentricity))
+    }
+
+    /// Time for one revolution, none for unbound orbits
+    pub fn period(&self, gravitational_parameter: f32) -> Option<f32> {
+        self.is_bound()
+            .then(|| TAU * (self.semi_major_axis.powi(3) / gravitational_parameter).sqrt())
+    }
+
+    /// Points along the orbit relative to the parent, unbound orbits are cut
+    /// off at `max_distance`
+    pub fn conic_points(&self, segments: usize, max_distance: f32) -> Vec<Vec2> {
+        let semi_latus_rectum = self.semi_latus_rectum();
+
+        //The true anomaly at which the orbit reaches the max distance
+        let limit = if self.is_bound() {
+            TAU / 2.0
+        } else {
+            ((semi_latus_rectum / max_distance - 1.0) / self.eccentricity)
+                .clamp(-1.0, 1.0)
+                .acos()
+        };
+
+        let rotation = Vec2::from_angle(self.argument_of_periapsis);
+        let sign = self.direction.sign();
+
+        (0..=segments)
+            .map(|i| {
+                let true_anomaly = -limit + 2.0 * limit * i as f32 / segments as f32;
+                let (sin, cos) = true_anomaly.sin_cos();
+                let distance = semi_latus_rectum / (1.0 + self.eccentricity * cos);
+
+                rotation.rotate(distance * vec2(cos, sign * sin))
+            })
+            .collect()
+    }
+
+    /// Position and velocity relative to the parent
+    ///
+    /// `gravitational_parameter` is the gravitational constant times the mass of both bodies,
+    /// or only the parent's mass if it is static. Returns none for degenerate orbits, where
+    /// the semi-latus rectum isn't positive, and for points of unbound orbits past their
+    /// asymptotes
+    pub fn relative_state(&self, gravitational_parameter: f32) -> Option<(Vec2, Vec2)> {
+        let eccentricity = self.eccentricity.max(0.0);
+        let semi_latus_rectum = self.semi_latus_rectum();
+        let (sin, cos) = self.true_anomaly.sin_cos();
+        let denominator = 1.0 + eccentricity * cos;
+
+        if !(semi_latus_rectum > 0.0 && gravitational_parameter > 0.0 && denominator > 0.0) {
+            return None;
+        }
+
+        let distance = semi_latus_rectum / denominator;
+        let speed = (gravitational_parameter / semi_latus_rectum).sqrt();
+
+        //Relative to the periapsis, mirrored for clockwise orbits
+        let sign = self.direction.sign();
+        let position = distance * vec2(cos, sign * sin);
+        let velocity = speed * vec2(-sin, sign * (eccentricity + cos));
+
+        let rotation = Vec2::from_angle(self.argument_of_periapsis);
+
+        Some((rotation.rotate(position), rotation.rotate(velocity)))
+    }
+}
+
+/// The body pulling hardest on a point, the one orbits around the point are measured against
+///
+/// Bodies are given as an id, their mass and their position, bodies exactly at the point are skipped
+pub fn dominant_attractor<T>(
+    position: Vec2,
+    bodies: impl IntoIterator<Item = (T, f32, Vec2)>,
+) -> Option<T> {
+    bodies
+        .into_iter()
+        .filter_map(|(id, mass, body_position)| {
+            let distance_squared = body_position.distance_squared(position);
+
+            (distance_squared > 0.0).then(|| (id, mass / distance_squared))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() <= 1e-3 * b.abs().max(1.0), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn relative_state_round_trips() {
+        let gravitational_parameter = 5000.0;
+        let orbits = [
+            OrbitalElements::circular(300.0, 1.0, OrbitDirection::Counterclockwise),
+            OrbitalElements {
+                semi_major_axis: 400.0,
+                eccentricity: 0.6,
+                argument_of_periapsis: -2.0,
+                true_anomaly: 0.8,
+                direction: OrbitDirection::Clockwise,
+            },
+            OrbitalElements {
+                semi_major_axis: -400.0,
+                eccentricity: 1.5,
+                argument_of_periapsis: 0.5,
+                true_anomaly: -1.0,
+                direction: OrbitDirection::Counterclockwise,
+            },
+        ];
+
+        for elements in orbits {
+            let (position, velocity) = elements.relative_state(gravitational_parameter).unwrap();
+            let found =
+                OrbitalElements::from_relative_state(position, velocity, gravitational_parameter)
+                    .unwrap();
+
+            assert_close(found.semi_major_axis, elements.semi_major_axis);
+            assert_close(found.eccentricity, elements.eccentricity);
+            assert_eq!(found.direction, elements.direction);
+
+            //The angles are only defined up to a turn
+            let (found_position, found_velocity) =
+                found.relative_state(gravitational_parameter).unwrap();
+            assert!(found_position.distance(position) <= 1e-3 * position.length());
+            assert!(found_velocity.distance(velocity) <= 1e-3 * velocity.length());
+        }
+    }
+
+    #[test]
+    fn degenerate_orbits_have_no_state() {
+        let mut elements = OrbitalElements::circular(0.0, 0.0, OrbitDirection::Clockwise);
+        assert_eq!(elements.relative_state(5000.0), None);
+
+        elements.semi_major_axis = 300.0;
+        elements.eccentricity = 1.0;
+        assert_eq!(elements.relative_state(5000.0), None);
+
+        elements.eccentricity = 0.0;
+        assert_eq!(elements.relative_state(0.0), None);
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use super::{
+    PhysicsSettings,
+    collision::{CollisionMode, pair_collision_mode},
+    integrator::Body,
+};
+
+/// A body taking part in a prediction
+#[derive(PartialEq)]
+pub(crate) struct PredictedBody {
+    pub body: Body,
+    pub radius: f32,
+    /// The [`CollisionPolicy`](super::collision::CollisionPolicy) of the body
+    pub policy: Option<CollisionMode>,
+    /// Only tracked bodies get a trajectory
+    pub tracked: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct Trajectory {
+    pub points: Vec<Vec2>,
+    /// Where the body first touches another body, the trajectory ends there
+    pub collision: Option<Vec2>,
+}
+
+/// Integrates copies of the bodies forward with the same force model as the simulation
+///
+/// Returns a trajectory for every tracked body, in the order they were given
+pub(crate) fn predict_trajectories(
+    settings: &PhysicsSettings,
+    bodies: &[PredictedBody],
+    delta: f32,
+    steps: usize,
+) -> Vec<Trajectory> {
+    let force_law = settings.force_law();
+    let accelerations = |bodies: &[Body]| settings.solver.accelerations(bodies, &force_law);
+
+    let mut state: Vec<Body> = bodies.iter().map(|b| b.body).collect();
+
+    //The stored accelerations may be stale or missing for bodies that don't exist yet
+    let initial_accelerations = accelerations(&state);
+    for (body, acceleration) in state.iter_mut().zip(initial_accelerations) {
+        body.acceleration = acceleration;
+    }
+
+    let tracked: Vec<usize> = (0..bodies.len()).filter(|&i| bodies[i].tracked).collect();
+    let mut trajectories: Vec<Trajectory> = tracked
+        .iter()
+        .map(|&i| Trajectory {
+            points: vec![state[i].position],
+            collision: None,
+        })
+        .collect();
+
+    for _ in 0..steps {
+        settings.integrator.step(&mut state, delta, accelerations);
+
+        let mut all_done = true;
+
+        for (trajectory, &i) in trajectories.iter_mut().zip(&tracked) {
+            if trajectory.collision.is_some() || !state[i].is_finite() {
+                continue;
+            }
+            all_done = false;
+
+            trajectory.points.push(state[i].position);
+            trajectory.collision = first_contact(settings, &state, bodies, i);
+        }
+
+        if all_done {
+            break;
+        }
+    }
+
+    trajectories
+}
+
+/// The contact point with the first body overlapping body i that it doesn't pass through
+fn first_contact(
+    settings: &PhysicsSettings,
+    state: &[Body],
+    bodies: &[PredictedBody],
+    i: usize,
+) -> Option<Vec2> {
+    let body = &state[i];
+
+    state.iter().enumerate().find_map(|(j, other)| {
+        let touching =
+            j != i && body.position.distance(other.position) < bodies[i].radius + bodies[j].radius;
+
+        //Keep the order collisions are resolved in, it decides ties in mass
+        let (first, second) = (i.min(j), i.max(j));
+        let collides = touching
+            && pair_collision_mode(
+                settings.collision_mode,
+                (state[first].mass, bodies[first].policy),
+                (state[second].mass, bodies[second].policy),
+            ) != CollisionMode::Ignore;
+
+        collides.then(|| {
+            body.position + (other.position - body.position).normalize_or_zero() * bodies[i].radius
+        })
+    })
+}
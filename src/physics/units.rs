@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+use super::DEFAULT_GRAVITATIONAL_CONSTANT;
+
+const METERS_PER_AU: f64 = 1.495_978_707e11;
+const KILOGRAMS_PER_SOLAR_MASS: f64 = 1.988_47e30;
+const SECONDS_PER_YEAR: f64 = 3.155_76e7;
+const SI_GRAVITATIONAL_CONSTANT: f64 = 6.674_30e-11;
+
+/// How many game units one astronomical unit is, chosen so the inner solar system fits on screen
+const GAME_UNITS_PER_AU: f64 = 100.0;
+const GAME_UNITS_PER_SOLAR_MASS: f64 = 1e5;
+/// How many simulated seconds one year takes
+const GAME_SECONDS_PER_YEAR: f64 = 60.0;
+
+/// Unit presets for level inputs
+///
+/// Everything is converted to game units when the level is built, the real unit
+/// systems share the same scale so an AU level and an SI level of the same
+/// system behave identically
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnitSystem {
+    /// The units the simulation runs in, nothing is converted
+    #[default]
+    Game,
+    /// Meters, kilograms and seconds
+    Si,
+    /// Astronomical units, solar masses and years
+    Astronomical,
+}
+
+/// How many game units one unit of the given unit system is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnitScale {
+    pub length: f32,
+    pub mass: f32,
+    pub time: f32,
+}
+
+impl UnitScale {
+    pub fn velocity(&self) -> f32 {
+        self.length / self.time
+    }
+
+    /// Converts a gravitational constant given in this unit system to game units
+    pub fn gravitational_constant(&self, gravitational_constant: f32) -> f32 {
+        let (length, mass, time) = (self.length as f64, self.mass as f64, self.time as f64);
+
+        (gravitational_constant as f64 * length.powi(3) / (mass * time.powi(2))) as f32
+    }
+}
+
+impl UnitSystem {
+    pub fn scale(self) -> UnitScale {
+        let (length, mass, time) = match self {
+            UnitSystem::Game => (1.0, 1.0, 1.0),
+            UnitSystem::Si => (
+                GAME_UNITS_PER_AU / METERS_PER_AU,
+                GAME_UNITS_PER_SOLAR_MASS / KILOGRAMS_PER_SOLAR_MASS,
+                GAME_SECONDS_PER_YEAR / SECONDS_PER_YEAR,
+            ),
+            UnitSystem::Astronomical => (
+                GAME_UNITS_PER_AU,
+                GAME_UNITS_PER_SOLAR_MASS,
+                GAME_SECONDS_PER_YEAR,
+            ),
+        };
+
+        UnitScale {
+            length: length as f32,
+            mass: mass as f32,
+            time: time as f32,
+        }
+    }
+
+    /// The real gravitational constant of this unit system, in this unit system
+    pub fn native_gravitational_constant(self) -> f32 {
+        match self {
+            UnitSystem::Game => DEFAULT_GRAVITATIONAL_CONSTANT,
+            UnitSystem::Si => SI_GRAVITATIONAL_CONSTANT as f32,
+            UnitSystem::Astronomical => {
+                (SI_GRAVITATIONAL_CONSTANT * KILOGRAMS_PER_SOLAR_MASS * SECONDS_PER_YEAR.powi(2)
+                    / METERS_PER_AU.powi(3)) as f32
+            }
+        }
+    }
+
+    /// The real gravitational constant of this unit system, in game units
+    pub fn gravitational_constant(self) -> f32 {
+        self.scale()
+            .gravitational_constant(self.native_gravitational_constant())
+    }
+}